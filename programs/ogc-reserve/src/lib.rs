@@ -1,75 +1,285 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Mint, Token, TokenAccount, transfer, Transfer};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, transfer_checked, TransferChecked, burn, Burn};
 use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+use anchor_lang::solana_program::hash::hashv;
 
 declare_id!("Bwombv4YnhcWAo7QHkqMsbem3Y88YdDStk6yn6FnNHTX");
 
-const ADMIN: &str = "oggzGFTgRM61YmhEbgWeivVmQx8bSAdBvsPGqN3ZfxN";
-const SECONDS_IN_DAY: u64 = 86400;
+const DEFAULT_NUM_FIELDS: u8 = 4;
+const MAX_NUM_FIELDS: u8 = 16;
+const DEFAULT_CLAIM_WINDOW_EPOCHS: u64 = 10;
+const MAX_LOCK_TIERS: usize = 8;
+const BPS_DENOMINATOR: u64 = 10000;
+
+pub const PAUSE_LOCK: u8 = 1 << 0;
+pub const PAUSE_UNLOCK: u8 = 1 << 1;
+pub const PAUSE_VOTE: u8 = 1 << 2;
+pub const PAUSE_CLAIM: u8 = 1 << 3;
+pub const PAUSE_NEW_EPOCH: u8 = 1 << 4;
+pub const PAUSE_ALL: u8 = PAUSE_LOCK | PAUSE_UNLOCK | PAUSE_VOTE | PAUSE_CLAIM | PAUSE_NEW_EPOCH;
+
 #[program]
 pub mod ogc_reserve {
     use super::*;
     pub fn initialize_first_epoch_account(ctx: Context<InitializeFirstEpochAccount>) -> Result<()> {
+        ctx.accounts.first_epoch_account.fields = vec![0; DEFAULT_NUM_FIELDS as usize];
         Ok(())
     }
     pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
-        // let unix_timestamp = Clock::get()?.unix_timestamp as u64;
-        // let days = unix_timestamp / SECONDS_IN_DAY;
-        ctx.accounts.global_data_account.epoch_lock_time = 1;
         ctx.accounts.global_data_account.epoch_end_time = 0;
         ctx.accounts.global_data_account.epoch_length = 1;
         ctx.accounts.global_data_account.reward_amount = 1000;
         ctx.accounts.global_data_account.ogc_mint = ctx.accounts.ogc_mint.key();
         ctx.accounts.global_data_account.ogg_mint = ctx.accounts.ogg_mint.key();
         ctx.accounts.global_data_account.fee_lamports = LAMPORTS_PER_SOL / 1000000;
+        ctx.accounts.global_data_account.pricing_curve = PricingCurve::Quadratic;
+        ctx.accounts.global_data_account.admin = ctx.accounts.signer.key();
+        ctx.accounts.global_data_account.num_fields = DEFAULT_NUM_FIELDS;
+        ctx.accounts.global_data_account.winner_rule = WinnerRule::SecondHighestWins;
+        ctx.accounts.global_data_account.claim_window_epochs = DEFAULT_CLAIM_WINDOW_EPOCHS;
+        ctx.accounts.global_data_account.lock_tiers = vec![LockTier { lock_epochs: 1, multiplier_bps: BPS_DENOMINATOR as u16 }];
+        Ok(())
+    }
+    pub fn propose_admin(ctx: Context<ProposeAdmin>, new_admin: Pubkey) -> Result<()> {
+        ctx.accounts.global_data_account.pending_admin = new_admin;
+        emit!(AdminProposed {
+            admin: ctx.accounts.global_data_account.admin,
+            pending_admin: new_admin,
+        });
         Ok(())
     }
-    pub fn modify_global_data(ctx: Context<ModifyGlobalData>, epoch_lock_time: u64, epoch_length: u64, reward_amount: u64) -> Result<()> {
-        ctx.accounts.global_data_account.epoch_lock_time = epoch_lock_time;
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        ctx.accounts.global_data_account.admin = ctx.accounts.new_admin.key();
+        ctx.accounts.global_data_account.pending_admin = Pubkey::default();
+        emit!(AdminAccepted {
+            admin: ctx.accounts.new_admin.key(),
+        });
+        Ok(())
+    }
+    pub fn modify_global_data(ctx: Context<ModifyGlobalData>, epoch_length: u64, reward_amount: u64, num_fields: u8, winner_rule: WinnerRule, claim_window_epochs: u64) -> Result<()> {
+        if !(2..=MAX_NUM_FIELDS).contains(&num_fields) {
+            return Err(CustomError::InvalidNumFields.into())
+        }
+        if let WinnerRule::Nth(k) = winner_rule {
+            if !(1..=num_fields).contains(&k) {
+                return Err(CustomError::InvalidWinnerRule.into())
+            }
+        }
+        if claim_window_epochs == 0 {
+            return Err(CustomError::InvalidClaimWindow.into())
+        }
+        if ctx.accounts.global_data_account.voting_cutoff >= epoch_length {
+            return Err(CustomError::InvalidVotingCutoff.into())
+        }
+        if ctx.accounts.global_data_account.commit_reveal && ctx.accounts.global_data_account.reveal_length >= epoch_length {
+            return Err(CustomError::InvalidRevealLength.into())
+        }
         ctx.accounts.global_data_account.reward_amount = reward_amount;
         ctx.accounts.global_data_account.epoch_length = epoch_length;
+        ctx.accounts.global_data_account.num_fields = num_fields;
+        ctx.accounts.global_data_account.winner_rule = winner_rule;
+        ctx.accounts.global_data_account.claim_window_epochs = claim_window_epochs;
+        emit!(ConfigChanged {
+            epoch_length,
+            reward_amount,
+            num_fields,
+            winner_rule,
+            claim_window_epochs,
+        });
+        Ok(())
+    }
+    pub fn set_vote_pricing(ctx: Context<SetVotePricing>, fee_lamports: u64, pricing_curve: PricingCurve, top_up_fee_lamports: u64) -> Result<()> {
+        ctx.accounts.global_data_account.fee_lamports = fee_lamports;
+        ctx.accounts.global_data_account.pricing_curve = pricing_curve;
+        ctx.accounts.global_data_account.top_up_fee_lamports = top_up_fee_lamports;
+        emit!(VotePricingChanged {
+            fee_lamports,
+            pricing_curve,
+            top_up_fee_lamports,
+        });
+        Ok(())
+    }
+    pub fn set_commit_reveal(ctx: Context<SetCommitReveal>, commit_reveal: bool, reveal_length: u64) -> Result<()> {
+        if commit_reveal && (reveal_length == 0 || reveal_length >= ctx.accounts.global_data_account.epoch_length) {
+            return Err(CustomError::InvalidRevealLength.into())
+        }
+        ctx.accounts.global_data_account.commit_reveal = commit_reveal;
+        ctx.accounts.global_data_account.reveal_length = reveal_length;
+        emit!(CommitRevealChanged {
+            commit_reveal,
+            reveal_length,
+        });
+        Ok(())
+    }
+    pub fn set_lock_tiers(ctx: Context<SetLockTiers>, lock_tiers: Vec<LockTier>) -> Result<()> {
+        if lock_tiers.is_empty() || lock_tiers.len() > MAX_LOCK_TIERS {
+            return Err(CustomError::InvalidLockTiers.into())
+        }
+        if lock_tiers.iter().any(|tier| tier.lock_epochs == 0 || tier.multiplier_bps == 0) {
+            return Err(CustomError::InvalidLockTiers.into())
+        }
+        ctx.accounts.global_data_account.lock_tiers = lock_tiers.clone();
+        emit!(LockTiersChanged {
+            lock_tiers,
+        });
+        Ok(())
+    }
+    // the schedule starts with the epoch in progress the first time it is set and keeps that start through later updates,
+    // so the curve cannot be restarted. when disabled new_epoch falls back to the flat reward_amount
+    pub fn set_emission_schedule(ctx: Context<SetEmissionSchedule>, enabled: bool, initial_amount: u64, initial_epochs: u64, halving_epochs: u64, total_cap: u64) -> Result<()> {
+        if enabled && halving_epochs == 0 {
+            return Err(CustomError::InvalidEmissionSchedule.into())
+        }
+        let start_epoch = match ctx.accounts.global_data_account.emission_schedule {
+            Some(emission_schedule) => emission_schedule.start_epoch,
+            None => ctx.accounts.global_data_account.epoch,
+        };
+        let emission_schedule = Some(EmissionSchedule {
+            enabled,
+            start_epoch,
+            initial_amount,
+            initial_epochs,
+            halving_epochs,
+            total_cap,
+        });
+        ctx.accounts.global_data_account.emission_schedule = emission_schedule;
+        emit!(EmissionScheduleChanged {
+            emission_schedule,
+        });
+        Ok(())
+    }
+    pub fn set_early_unlock(ctx: Context<SetEarlyUnlock>, enabled: bool, penalty_bps: u16, linear_decay: bool, burn_penalty: bool) -> Result<()> {
+        if penalty_bps as u64 > BPS_DENOMINATOR {
+            return Err(CustomError::InvalidPenalty.into())
+        }
+        // penalties can only be added to the reward pool when it holds the staked token
+        if !burn_penalty && ctx.accounts.global_data_account.ogc_mint != ctx.accounts.global_data_account.ogg_mint {
+            return Err(CustomError::InvalidMintAccount.into())
+        }
+        ctx.accounts.global_data_account.early_unlock_enabled = enabled;
+        ctx.accounts.global_data_account.early_unlock_penalty_bps = penalty_bps;
+        ctx.accounts.global_data_account.early_unlock_linear_decay = linear_decay;
+        ctx.accounts.global_data_account.early_unlock_burn = burn_penalty;
+        emit!(EarlyUnlockChanged {
+            enabled,
+            penalty_bps,
+            linear_decay,
+            burn_penalty,
+        });
+        Ok(())
+    }
+    pub fn set_voting_cutoff(ctx: Context<SetVotingCutoff>, voting_cutoff: u64) -> Result<()> {
+        if voting_cutoff >= ctx.accounts.global_data_account.epoch_length {
+            return Err(CustomError::InvalidVotingCutoff.into())
+        }
+        ctx.accounts.global_data_account.voting_cutoff = voting_cutoff;
+        emit!(VotingCutoffChanged {
+            voting_cutoff,
+        });
+        Ok(())
+    }
+    pub fn set_pause(ctx: Context<SetPause>, paused_flags: u8) -> Result<()> {
+        if paused_flags & !PAUSE_ALL != 0 {
+            return Err(CustomError::InvalidPauseFlags.into())
+        }
+        ctx.accounts.global_data_account.paused_flags = paused_flags;
+        emit!(PauseChanged {
+            paused_flags,
+        });
         Ok(())
     }
+    // deposit_ogg and withdraw_ogg move the reward token (ogc_mint), the names predate the split
     pub fn deposit_ogg(ctx: Context<DepositOgg>, amount: u64) -> Result<()> {
-        transfer(
+        let balance_before = ctx.accounts.program_holder_account.amount;
+        transfer_checked(
             CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
-                Transfer {
+                TransferChecked {
                     from: ctx.accounts.signer_token_account.to_account_info(),
+                    mint: ctx.accounts.ogc_mint.to_account_info(),
                     to: ctx.accounts.program_holder_account.to_account_info(),
                     authority: ctx.accounts.signer.to_account_info()
                 }
             ),
-            amount
+            amount,
+            ctx.accounts.ogc_mint.decimals
         )?;
+        ctx.accounts.program_holder_account.reload()?;
+        let amount = ctx.accounts.program_holder_account.amount - balance_before;
+        emit!(RewardsDeposited {
+            depositor: ctx.accounts.signer.key(),
+            amount,
+        });
         Ok(())
     }
-    pub fn withdraw_sol(ctx: Context<WithdrawSol>) -> Result<()> {
-        if ADMIN.parse::<Pubkey>().unwrap() != ctx.accounts.signer.key() {
-            return Err(CustomError::InvalidSigner.into())
+    // funds the pool for an epoch in any mint, the pool and its vault are created by the first deposit
+    pub fn deposit_reward(ctx: Context<DepositReward>, epoch: u64, amount: u64) -> Result<()> {
+        if ctx.accounts.reward_pool.vault == Pubkey::default() {
+            ctx.accounts.reward_pool.epoch = epoch;
+            ctx.accounts.reward_pool.mint = ctx.accounts.mint.key();
+            ctx.accounts.reward_pool.vault = ctx.accounts.reward_pool_vault.key();
         }
+        let balance_before = ctx.accounts.reward_pool_vault.amount;
+        transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.signer_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.reward_pool_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info()
+                }
+            ),
+            amount,
+            ctx.accounts.mint.decimals
+        )?;
+        ctx.accounts.reward_pool_vault.reload()?;
+        let amount = ctx.accounts.reward_pool_vault.amount - balance_before;
+        ctx.accounts.reward_pool.amount += amount;
+        emit!(PoolRewardsDeposited {
+            depositor: ctx.accounts.signer.key(),
+            epoch,
+            mint: ctx.accounts.mint.key(),
+            amount,
+        });
+        Ok(())
+    }
+    pub fn withdraw_sol(ctx: Context<WithdrawSol>) -> Result<()> {
         let min_rent = Rent::get()?.minimum_balance(8) + 20;
-        let transfer = ctx.accounts.program_authority.get_lamports() - min_rent;
-        if transfer <= 0 {
+        let transfer = ctx.accounts.program_authority.get_lamports().saturating_sub(min_rent);
+        if transfer == 0 {
             return Err(CustomError::NoFeesToWithdraw.into())
         }
         **ctx.accounts.program_authority.try_borrow_mut_lamports()? -= transfer;
         **ctx.accounts.signer.try_borrow_mut_lamports()? += transfer;
+        emit!(FeesWithdrawn {
+            admin: ctx.accounts.signer.key(),
+            lamports: transfer,
+        });
         Ok(())
     }
     pub fn withdraw_ogg(ctx: Context<WithdrawOgg>, amount: u64) -> Result<()> {
-        transfer(
+        if amount > ctx.accounts.program_holder_account.amount.saturating_sub(ctx.accounts.global_data_account.reserved) {
+            return Err(CustomError::ExceedsUnreservedBalance.into())
+        }
+        transfer_checked(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                Transfer {
+                TransferChecked {
                     from: ctx.accounts.program_holder_account.to_account_info(),
+                    mint: ctx.accounts.ogc_mint.to_account_info(),
                     to: ctx.accounts.signer_token_account.to_account_info(),
                     authority: ctx.accounts.program_authority.to_account_info()
                 },
                 &[&[b"auth", &[ctx.bumps.program_authority]]]
             ),
             amount,
+            ctx.accounts.ogc_mint.decimals,
         )?;
+        emit!(RewardsWithdrawn {
+            admin: ctx.accounts.signer.key(),
+            amount,
+        });
         Ok(())
     }
     pub fn new_epoch(ctx: Context<NewEpoch>, epoch: u64) -> Result<()> {
@@ -80,32 +290,58 @@ pub mod ogc_reserve {
         ctx.accounts.global_data_account.epoch += 1;
         let steps = time / ctx.accounts.global_data_account.epoch_length;
         ctx.accounts.global_data_account.epoch_end_time = (steps + 1) * ctx.accounts.global_data_account.epoch_length;
-        let mut max: u64 = 0;
-        let mut max_index: usize = 0;
-        let mut second_max: u64 = 0;
-        let mut second_max_index: usize = 0;
-        for i in 0..4 {
-            if ctx.accounts.prev_epoch_account.fields[i] > second_max {
-                if ctx.accounts.prev_epoch_account.fields[i] > max {
-                    second_max = max;
-                    second_max_index = max_index;
-                    max = ctx.accounts.prev_epoch_account.fields[i];
-                    max_index = i;
-                } else {
-                    second_max = ctx.accounts.prev_epoch_account.fields[i];
-                    second_max_index = i;
-                }
+        ctx.accounts.epoch_account.fields = vec![0; ctx.accounts.global_data_account.num_fields as usize];
+        if ctx.accounts.global_data_account.commit_reveal {
+            ctx.accounts.epoch_account.commit_reveal = true;
+            ctx.accounts.epoch_account.reveal_start_time = ctx.accounts.global_data_account.epoch_end_time.saturating_sub(ctx.accounts.global_data_account.reveal_length);
+        }
+        let winner_rule = ctx.accounts.global_data_account.winner_rule;
+        let outcome = winner_rule.select(&ctx.accounts.prev_epoch_account.fields);
+        ctx.accounts.prev_epoch_account.winner_rule = winner_rule;
+        let scheduled = ctx.accounts.global_data_account.emission_schedule.filter(|emission_schedule| emission_schedule.enabled);
+        // scheduled emission still waiting in the rollover already counts against the cap
+        let pending_emission = ctx.accounts.global_data_account.rollover_emission;
+        let (emission, scheduled_emission) = match scheduled {
+            Some(emission_schedule) => {
+                let emission = emission_schedule.emission(epoch - 1, ctx.accounts.global_data_account.total_emitted + pending_emission);
+                (emission, emission)
             }
+            // flat reward_amount epochs are outside the schedule
+            None => (ctx.accounts.global_data_account.reward_amount, 0),
+        };
+        let reward = emission + ctx.accounts.global_data_account.rollover;
+        if outcome == EpochOutcome::NoWinner {
+            ctx.accounts.global_data_account.rollover = reward;
+            ctx.accounts.global_data_account.rollover_emission = pending_emission + scheduled_emission;
+            ctx.accounts.prev_epoch_account.reward = 0;
+        } else {
+            // only what the holder account can actually cover is promised to claimants
+            let available = ctx.accounts.program_holder_account.amount.saturating_sub(ctx.accounts.global_data_account.reserved);
+            let funded = std::cmp::min(reward, available);
+            ctx.accounts.global_data_account.rollover = 0;
+            ctx.accounts.global_data_account.reserved += funded;
+            ctx.accounts.prev_epoch_account.reward = funded;
+            // scheduled emission only counts once it is funded, the unfunded shortfall is never paid out
+            ctx.accounts.global_data_account.rollover_emission = 0;
+            ctx.accounts.global_data_account.total_emitted += (pending_emission + scheduled_emission).saturating_sub(reward - funded);
         }
-        ctx.accounts.prev_epoch_account.winner = second_max_index as u64;
-        ctx.accounts.prev_epoch_account.reward = ctx.accounts.global_data_account.reward_amount;
+        ctx.accounts.prev_epoch_account.outcome = outcome.clone();
+        emit!(EpochAdvanced {
+            epoch: epoch - 1,
+            outcome,
+            fields: ctx.accounts.prev_epoch_account.fields.clone(),
+            reward: ctx.accounts.prev_epoch_account.reward,
+            emission,
+            rollover: ctx.accounts.global_data_account.rollover,
+            epoch_end_time: ctx.accounts.global_data_account.epoch_end_time,
+        });
         Ok(())
     }
     pub fn create_stats_account(ctx: Context<CreateStatsAccount>) -> Result<()> {
         ctx.accounts.user_stats_account.owner = ctx.accounts.signer.key();
         Ok(())
     }
-    pub fn create_data_account(ctx: Context<CreateDataAccount>) -> Result<()> {
+    pub fn create_data_account(_ctx: Context<CreateDataAccount>) -> Result<()> {
         Ok(())
     }
     pub fn create_lock_account(ctx: Context<CreateLockAccount>, epoch: u64) -> Result<()> {
@@ -113,110 +349,668 @@ pub mod ogc_reserve {
         ctx.accounts.lock_account.owner = ctx.accounts.signer.key();
         Ok(())
     }
-    pub fn lock(ctx: Context<Lock>, epoch: u64, amount: u64) -> Result<()> {
+    pub fn lock(ctx: Context<Lock>, epoch: u64, amount: u64, tier: u8) -> Result<()> {
+        let Some(&lock_tier) = ctx.accounts.global_data_account.lock_tiers.get(tier as usize) else {
+            return Err(CustomError::InvalidLockTier.into())
+        };
+        if ctx.accounts.lock_account.amount > 0 && ctx.accounts.lock_account.tier != tier {
+            return Err(CustomError::InvalidLockTier.into())
+        }
         // no point in this check
         // let time = Clock::get()?.unix_timestamp as u64;
         // if ctx.accounts.global_data_account.epoch_end_time > time {
         //     return Err(CustomError::EpochExpired.into())
         // }
-        transfer(
+        let balance_before = ctx.accounts.signer_holder_account.amount;
+        transfer_checked(
             CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
-                Transfer {
+                TransferChecked {
                     from: ctx.accounts.signer_token_account.to_account_info(),
+                    mint: ctx.accounts.ogg_mint.to_account_info(),
                     to: ctx.accounts.signer_holder_account.to_account_info(),
                     authority: ctx.accounts.signer.to_account_info()
                 }
             ),
-            amount
+            amount,
+            ctx.accounts.ogg_mint.decimals
         )?;
-        ctx.accounts.lock_account.unlock_epoch = ctx.accounts.global_data_account.epoch + ctx.accounts.global_data_account.epoch_lock_time;
+        // transfer fee mints deliver less than requested, only credit what arrived
+        ctx.accounts.signer_holder_account.reload()?;
+        let amount = ctx.accounts.signer_holder_account.amount - balance_before;
+        let power = lock_tier.voting_power(amount);
+        // never shorten a position that was already extended
+        ctx.accounts.lock_account.unlock_epoch = std::cmp::max(ctx.accounts.lock_account.unlock_epoch, ctx.accounts.global_data_account.epoch + lock_tier.lock_epochs);
+        if ctx.accounts.lock_account.amount == 0 {
+            ctx.accounts.lock_account.start_epoch = ctx.accounts.global_data_account.epoch;
+        }
+        ctx.accounts.lock_account.tier = tier;
+        ctx.accounts.lock_account.multiplier_bps = lock_tier.multiplier_bps;
         ctx.accounts.lock_account.amount += amount;
+        ctx.accounts.lock_account.power += power;
         ctx.accounts.user_data_account.amount += amount;
+        ctx.accounts.user_data_account.checkpoint(ctx.accounts.global_data_account.epoch);
+        ctx.accounts.user_data_account.voting_power += power;
+        emit!(Locked {
+            owner: ctx.accounts.signer.key(),
+            epoch,
+            amount,
+            unlock_epoch: ctx.accounts.lock_account.unlock_epoch,
+            tier,
+            power,
+        });
         Ok(())
     }
     pub fn unlock(ctx: Context<Unlock>, epoch: u64, amount: u64) -> Result<()> {
         if amount > ctx.accounts.lock_account.amount {
             return Err(CustomError::ExceedsBalanceOfLockAccount.into())
         }
-        transfer(
+        let power = ctx.accounts.lock_account.power_for(amount);
+        // votes already cast this epoch must stay backed by locked tokens
+        if ctx.accounts.user_data_account.epoch == ctx.accounts.global_data_account.epoch && ctx.accounts.user_data_account.voting_power - power < ctx.accounts.user_data_account.staked {
+            return Err(CustomError::NotEnoughStaked.into())
+        }
+        transfer_checked(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                Transfer {
+                TransferChecked {
                     from: ctx.accounts.signer_holder_account.to_account_info(),
+                    mint: ctx.accounts.ogg_mint.to_account_info(),
                     to: ctx.accounts.signer_token_account.to_account_info(),
                     authority: ctx.accounts.program_authority.to_account_info()
                 },
                 &[&[b"auth", &[ctx.bumps.program_authority]]]
             ),
-            amount
+            amount,
+            ctx.accounts.ogg_mint.decimals
+        )?;
+        ctx.accounts.lock_account.amount -= amount;
+        ctx.accounts.lock_account.power -= power;
+        ctx.accounts.user_data_account.amount -= amount;
+        ctx.accounts.user_data_account.checkpoint(ctx.accounts.global_data_account.epoch);
+        ctx.accounts.user_data_account.voting_power -= power;
+        if ctx.accounts.lock_account.amount == 0 {
+            ctx.accounts.lock_account.close(ctx.accounts.signer.to_account_info())?;
+        }
+        emit!(Unlocked {
+            owner: ctx.accounts.signer.key(),
+            epoch,
+            amount,
+        });
+        Ok(())
+    }
+    pub fn early_unlock(ctx: Context<EarlyUnlock>, epoch: u64, amount: u64) -> Result<()> {
+        if !ctx.accounts.global_data_account.early_unlock_enabled {
+            return Err(CustomError::EarlyUnlockDisabled.into())
+        }
+        if amount > ctx.accounts.lock_account.amount {
+            return Err(CustomError::ExceedsBalanceOfLockAccount.into())
+        }
+        let power = ctx.accounts.lock_account.power_for(amount);
+        if ctx.accounts.user_data_account.epoch == ctx.accounts.global_data_account.epoch && ctx.accounts.user_data_account.voting_power - power < ctx.accounts.user_data_account.staked {
+            return Err(CustomError::NotEnoughStaked.into())
+        }
+        let penalty = ctx.accounts.global_data_account.early_unlock_penalty(&ctx.accounts.lock_account, amount);
+        let burned = ctx.accounts.global_data_account.early_unlock_burn;
+        let signer_seeds: &[&[&[u8]]] = &[&[b"auth", &[ctx.bumps.program_authority]]];
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.signer_holder_account.to_account_info(),
+                    mint: ctx.accounts.ogg_mint.to_account_info(),
+                    to: ctx.accounts.signer_token_account.to_account_info(),
+                    authority: ctx.accounts.program_authority.to_account_info()
+                },
+                signer_seeds
+            ),
+            amount - penalty,
+            ctx.accounts.ogg_mint.decimals
         )?;
+        if penalty > 0 {
+            if burned {
+                burn(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        Burn {
+                            mint: ctx.accounts.ogg_mint.to_account_info(),
+                            from: ctx.accounts.signer_holder_account.to_account_info(),
+                            authority: ctx.accounts.program_authority.to_account_info()
+                        },
+                        signer_seeds
+                    ),
+                    penalty
+                )?;
+            } else {
+                let balance_before = ctx.accounts.program_holder_account.amount;
+                transfer_checked(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        TransferChecked {
+                            from: ctx.accounts.signer_holder_account.to_account_info(),
+                            mint: ctx.accounts.ogg_mint.to_account_info(),
+                            to: ctx.accounts.program_holder_account.to_account_info(),
+                            authority: ctx.accounts.program_authority.to_account_info()
+                        },
+                        signer_seeds
+                    ),
+                    penalty,
+                    ctx.accounts.ogg_mint.decimals
+                )?;
+                // the penalty goes to voters through the next epoch's reward, not to the admin's unreserved balance
+                ctx.accounts.program_holder_account.reload()?;
+                ctx.accounts.global_data_account.rollover += ctx.accounts.program_holder_account.amount - balance_before;
+            }
+        }
         ctx.accounts.lock_account.amount -= amount;
+        ctx.accounts.lock_account.power -= power;
         ctx.accounts.user_data_account.amount -= amount;
+        ctx.accounts.user_data_account.checkpoint(ctx.accounts.global_data_account.epoch);
+        ctx.accounts.user_data_account.voting_power -= power;
         if ctx.accounts.lock_account.amount == 0 {
             ctx.accounts.lock_account.close(ctx.accounts.signer.to_account_info())?;
         }
+        emit!(EarlyUnlocked {
+            owner: ctx.accounts.signer.key(),
+            epoch,
+            amount,
+            penalty,
+            burned,
+        });
+        Ok(())
+    }
+    pub fn extend_lock(ctx: Context<ExtendLock>, epoch: u64, tier: u8) -> Result<()> {
+        let Some(&lock_tier) = ctx.accounts.global_data_account.lock_tiers.get(tier as usize) else {
+            return Err(CustomError::InvalidLockTier.into())
+        };
+        let unlock_epoch = ctx.accounts.global_data_account.epoch + lock_tier.lock_epochs;
+        if unlock_epoch <= ctx.accounts.lock_account.unlock_epoch {
+            return Err(CustomError::InvalidLockExtension.into())
+        }
+        let power = lock_tier.voting_power(ctx.accounts.lock_account.amount);
+        ctx.accounts.user_data_account.checkpoint(ctx.accounts.global_data_account.epoch);
+        ctx.accounts.user_data_account.voting_power = ctx.accounts.user_data_account.voting_power - ctx.accounts.lock_account.power + power;
+        if ctx.accounts.user_data_account.epoch == ctx.accounts.global_data_account.epoch && ctx.accounts.user_data_account.voting_power < ctx.accounts.user_data_account.staked {
+            return Err(CustomError::NotEnoughStaked.into())
+        }
+        ctx.accounts.lock_account.unlock_epoch = unlock_epoch;
+        ctx.accounts.lock_account.tier = tier;
+        ctx.accounts.lock_account.multiplier_bps = lock_tier.multiplier_bps;
+        ctx.accounts.lock_account.power = power;
+        emit!(LockExtended {
+            owner: ctx.accounts.signer.key(),
+            epoch,
+            unlock_epoch,
+            tier,
+            power,
+        });
+        Ok(())
+    }
+    // a matured lock no longer earns its tier multiplier, anyone can drop it back to 1x until it is extended or re-locked
+    pub fn settle_lock(ctx: Context<SettleLock>, owner: Pubkey, epoch: u64) -> Result<()> {
+        let power = ctx.accounts.lock_account.amount;
+        ctx.accounts.user_data_account.checkpoint(ctx.accounts.global_data_account.epoch);
+        ctx.accounts.user_data_account.voting_power = ctx.accounts.user_data_account.voting_power - ctx.accounts.lock_account.power + power;
+        ctx.accounts.lock_account.multiplier_bps = BPS_DENOMINATOR as u16;
+        ctx.accounts.lock_account.power = power;
+        emit!(LockSettled {
+            owner,
+            epoch,
+            power,
+        });
+        Ok(())
+    }
+    // folds source_epoch's lock into the later-maturing lock at epoch, which keeps its tier.
+    // like lock, the merged position stays locked for at least the full tier duration from now
+    pub fn merge_locks(ctx: Context<MergeLocks>, epoch: u64, source_epoch: u64) -> Result<()> {
+        let Some(&tier) = ctx.accounts.global_data_account.lock_tiers.get(ctx.accounts.lock_account.tier as usize) else {
+            return Err(CustomError::InvalidLockTier.into())
+        };
+        let unlock_epoch = std::cmp::max(ctx.accounts.lock_account.unlock_epoch, ctx.accounts.global_data_account.epoch + tier.lock_epochs);
+        let amount = ctx.accounts.lock_account.amount + ctx.accounts.source_lock_account.amount;
+        let lock_tier = LockTier { lock_epochs: 0, multiplier_bps: ctx.accounts.lock_account.multiplier_bps };
+        let power = lock_tier.voting_power(amount);
+        let previous_power = ctx.accounts.lock_account.power + ctx.accounts.source_lock_account.power;
+        ctx.accounts.user_data_account.checkpoint(ctx.accounts.global_data_account.epoch);
+        ctx.accounts.user_data_account.voting_power = ctx.accounts.user_data_account.voting_power - previous_power + power;
+        if ctx.accounts.user_data_account.epoch == ctx.accounts.global_data_account.epoch && ctx.accounts.user_data_account.voting_power < ctx.accounts.user_data_account.staked {
+            return Err(CustomError::NotEnoughStaked.into())
+        }
+        ctx.accounts.lock_account.unlock_epoch = unlock_epoch;
+        ctx.accounts.lock_account.amount = amount;
+        ctx.accounts.lock_account.power = power;
+        ctx.accounts.source_lock_account.amount = 0;
+        ctx.accounts.source_lock_account.power = 0;
+        ctx.accounts.source_lock_account.close(ctx.accounts.signer.to_account_info())?;
+        emit!(LocksMerged {
+            owner: ctx.accounts.signer.key(),
+            epoch,
+            source_epoch,
+            amount,
+            unlock_epoch,
+            power,
+        });
+        Ok(())
+    }
+    // split_epoch only names the split off lock account, it keeps the original lock's maturity and tier
+    pub fn split_lock(ctx: Context<SplitLock>, epoch: u64, split_epoch: u64, amount: u64) -> Result<()> {
+        if amount == 0 || amount >= ctx.accounts.lock_account.amount {
+            return Err(CustomError::ExceedsBalanceOfLockAccount.into())
+        }
+        let power = ctx.accounts.lock_account.power_for(amount);
+        ctx.accounts.lock_account.amount -= amount;
+        ctx.accounts.lock_account.power -= power;
+        ctx.accounts.split_lock_account.epoch = split_epoch;
+        ctx.accounts.split_lock_account.owner = ctx.accounts.signer.key();
+        ctx.accounts.split_lock_account.unlock_epoch = ctx.accounts.lock_account.unlock_epoch;
+        ctx.accounts.split_lock_account.start_epoch = ctx.accounts.lock_account.start_epoch;
+        ctx.accounts.split_lock_account.tier = ctx.accounts.lock_account.tier;
+        ctx.accounts.split_lock_account.multiplier_bps = ctx.accounts.lock_account.multiplier_bps;
+        ctx.accounts.split_lock_account.amount = amount;
+        ctx.accounts.split_lock_account.power = power;
+        emit!(LockSplit {
+            owner: ctx.accounts.signer.key(),
+            epoch,
+            split_epoch,
+            amount,
+        });
         Ok(())
     }
-    pub fn create_vote_account(ctx: Context<CreateVoteAccount>, epoch: u64) -> Result<()> {
+    pub fn create_vote_account(ctx: Context<CreateVoteAccount>, _epoch: u64) -> Result<()> {
+        ctx.accounts.vote_account.fields = vec![0; ctx.accounts.epoch_account.fields.len()];
+        ctx.accounts.vote_account.delegated_fields = vec![0; ctx.accounts.epoch_account.fields.len()];
         Ok(())
     }
-    pub fn vote(ctx: Context<Vote>, epoch: u64, amounts: [u64; 4]) -> Result<()> {
+    pub fn get_vote_price(ctx: Context<GetVotePrice>, epoch: u64) -> Result<u64> {
+        let first_vote = epoch != ctx.accounts.user_data_account.epoch;
+        Ok(ctx.accounts.global_data_account.vote_price(ctx.accounts.epoch_account.voters, first_vote))
+    }
+    pub fn vote(ctx: Context<Vote>, epoch: u64, amounts: Vec<u64>, max_fee_lamports: u64) -> Result<()> {
         if epoch != ctx.accounts.global_data_account.epoch {
             return Err(CustomError::IncorrectEpochNum.into())
         }
-        let price = ctx.accounts.global_data_account.fee_lamports * ctx.accounts.epoch_account.voters.pow(2);
-        anchor_lang::system_program::transfer(
-            CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.signer.to_account_info(),
-                    to: ctx.accounts.program_authority.to_account_info(),
-                }
-            ),
-            price,
-        )?;
-        if epoch != ctx.accounts.user_data_account.epoch {
+        let time = Clock::get()?.unix_timestamp as u64;
+        if time >= ctx.accounts.global_data_account.voting_deadline() {
+            return Err(CustomError::EpochExpired.into())
+        }
+        if amounts.len() > ctx.accounts.epoch_account.fields.len() {
+            return Err(CustomError::InvalidFieldIndex.into())
+        }
+        if ctx.accounts.epoch_account.commit_reveal {
+            return Err(CustomError::CommitRevealRequired.into())
+        }
+        let first_vote = epoch != ctx.accounts.user_data_account.epoch;
+        let price = ctx.accounts.global_data_account.vote_price(ctx.accounts.epoch_account.voters, first_vote);
+        charge_vote_fee(&ctx.accounts.signer, &ctx.accounts.program_authority, &ctx.accounts.system_program, price, max_fee_lamports)?;
+        tally_vote(&mut ctx.accounts.epoch_account, &mut ctx.accounts.vote_account, &mut ctx.accounts.user_data_account, epoch, &amounts, first_vote)?;
+        ctx.accounts.user_stats_account.active_reserve_epochs += 1;
+        ctx.accounts.vote_account.owner = ctx.accounts.signer.key();
+        emit!(Voted {
+            voter: ctx.accounts.signer.key(),
+            epoch,
+            amounts,
+            fee_lamports: price,
+        });
+        Ok(())
+    }
+    // replaces the signer's allocation for the epoch, so it can move votes between fields or drop them,
+    // raising the total allocation pays the top up fee like voting again would
+    pub fn revote(ctx: Context<Vote>, epoch: u64, amounts: Vec<u64>, max_fee_lamports: u64) -> Result<()> {
+        if epoch != ctx.accounts.global_data_account.epoch || epoch != ctx.accounts.user_data_account.epoch {
+            return Err(CustomError::IncorrectEpochNum.into())
+        }
+        let time = Clock::get()?.unix_timestamp as u64;
+        if time >= ctx.accounts.global_data_account.voting_deadline() {
+            return Err(CustomError::EpochExpired.into())
+        }
+        if ctx.accounts.epoch_account.commit_reveal {
+            return Err(CustomError::CommitRevealVotesFinal.into())
+        }
+        if amounts.len() > ctx.accounts.epoch_account.fields.len() {
+            return Err(CustomError::InvalidFieldIndex.into())
+        }
+        let mut old_sum: u64 = 0;
+        let mut new_sum: u64 = 0;
+        for i in 0..ctx.accounts.vote_account.fields.len() {
+            let old = ctx.accounts.vote_account.fields[i];
+            let new = amounts.get(i).copied().unwrap_or(0);
+            ctx.accounts.epoch_account.fields[i] = ctx.accounts.epoch_account.fields[i] - old + new;
+            ctx.accounts.vote_account.fields[i] = new;
+            // the delegate keeps credit only for the votes that are still there
+            ctx.accounts.vote_account.delegated_fields[i] = std::cmp::min(ctx.accounts.vote_account.delegated_fields[i], new);
+            old_sum += old;
+            new_sum += new;
+        }
+        let staked = ctx.accounts.user_data_account.staked - old_sum + new_sum;
+        if staked > ctx.accounts.user_data_account.epoch_power(epoch) {
+            return Err(CustomError::NotEnoughStaked.into())
+        }
+        ctx.accounts.user_data_account.staked = staked;
+        let price = if new_sum > old_sum { ctx.accounts.global_data_account.top_up_fee_lamports } else { 0 };
+        charge_vote_fee(&ctx.accounts.signer, &ctx.accounts.program_authority, &ctx.accounts.system_program, price, max_fee_lamports)?;
+        emit!(Revoted {
+            voter: ctx.accounts.signer.key(),
+            epoch,
+            amounts,
+            fee_lamports: price,
+        });
+        Ok(())
+    }
+    pub fn unvote(ctx: Context<Vote>, epoch: u64) -> Result<()> {
+        revote(ctx, epoch, vec![], 0)
+    }
+    // commitment is sha256 over the voter, the little-endian epoch, the little-endian bytes of each amount and the salt,
+    // so a commitment can't be copied by another voter or replayed in a later epoch
+    pub fn commit_vote(ctx: Context<Vote>, epoch: u64, commitment: [u8; 32], reserved: u64, max_fee_lamports: u64) -> Result<()> {
+        if epoch != ctx.accounts.global_data_account.epoch {
+            return Err(CustomError::IncorrectEpochNum.into())
+        }
+        if !ctx.accounts.epoch_account.commit_reveal {
+            return Err(CustomError::CommitRevealDisabled.into())
+        }
+        let time = Clock::get()?.unix_timestamp as u64;
+        if time >= ctx.accounts.global_data_account.voting_deadline() {
+            return Err(CustomError::EpochExpired.into())
+        }
+        if time >= ctx.accounts.epoch_account.reveal_start_time {
+            return Err(CustomError::CommitPhaseOver.into())
+        }
+        if ctx.accounts.vote_account.commitment != [0; 32] {
+            return Err(CustomError::AlreadyCommitted.into())
+        }
+        let first_vote = epoch != ctx.accounts.user_data_account.epoch;
+        let price = ctx.accounts.global_data_account.vote_price(ctx.accounts.epoch_account.voters, first_vote);
+        charge_vote_fee(&ctx.accounts.signer, &ctx.accounts.program_authority, &ctx.accounts.system_program, price, max_fee_lamports)?;
+        if first_vote {
             ctx.accounts.user_data_account.epoch = epoch;
             ctx.accounts.user_data_account.staked = 0;
             ctx.accounts.epoch_account.voters += 1;
         }
-        let mut sum: u64 = 0;
-        for i in 0..4 {
-            ctx.accounts.epoch_account.fields[i] += amounts[i];
-            ctx.accounts.vote_account.fields[i] += amounts[i];
-            sum += amounts[i];
+        if ctx.accounts.user_data_account.staked + reserved > ctx.accounts.user_data_account.epoch_power(epoch) {
+            return Err(CustomError::NotEnoughStaked.into())
         }
+        ctx.accounts.user_data_account.staked += reserved;
+        ctx.accounts.vote_account.commitment = commitment;
+        ctx.accounts.vote_account.reserved = reserved;
         ctx.accounts.vote_account.epoch = epoch;
-        ctx.accounts.user_stats_account.active_reserve_epochs += 1;
         ctx.accounts.vote_account.owner = ctx.accounts.signer.key();
-        if ctx.accounts.user_data_account.staked + sum > ctx.accounts.user_data_account.amount {
+        ctx.accounts.user_stats_account.active_reserve_epochs += 1;
+        emit!(VoteCommitted {
+            voter: ctx.accounts.signer.key(),
+            epoch,
+            reserved,
+            fee_lamports: price,
+        });
+        Ok(())
+    }
+    // votes that are never revealed keep their fee paid and their vote account empty, so they claim nothing
+    pub fn reveal_vote(ctx: Context<Vote>, epoch: u64, amounts: Vec<u64>, salt: [u8; 32]) -> Result<()> {
+        if epoch != ctx.accounts.global_data_account.epoch {
+            return Err(CustomError::IncorrectEpochNum.into())
+        }
+        let time = Clock::get()?.unix_timestamp as u64;
+        if time < ctx.accounts.epoch_account.reveal_start_time || time >= ctx.accounts.global_data_account.epoch_end_time {
+            return Err(CustomError::NotRevealPhase.into())
+        }
+        if ctx.accounts.vote_account.revealed {
+            return Err(CustomError::AlreadyRevealed.into())
+        }
+        if amounts.len() > ctx.accounts.epoch_account.fields.len() {
+            return Err(CustomError::InvalidFieldIndex.into())
+        }
+        let amount_bytes: Vec<u8> = amounts.iter().flat_map(|amount| amount.to_le_bytes()).collect();
+        if ctx.accounts.vote_account.commitment == [0; 32] || hashv(&[ctx.accounts.signer.key().as_ref(), &epoch.to_le_bytes(), &amount_bytes, &salt]).to_bytes() != ctx.accounts.vote_account.commitment {
+            return Err(CustomError::InvalidReveal.into())
+        }
+        let mut sum: u64 = 0;
+        for (i, amount) in amounts.iter().enumerate() {
+            ctx.accounts.epoch_account.fields[i] += amount;
+            ctx.accounts.vote_account.fields[i] += amount;
+            sum += amount;
+        }
+        if sum > ctx.accounts.vote_account.reserved {
             return Err(CustomError::NotEnoughStaked.into())
         }
-        ctx.accounts.user_data_account.staked += sum;
+        // release whatever was reserved but not used by the revealed amounts
+        ctx.accounts.user_data_account.staked -= ctx.accounts.vote_account.reserved - sum;
+        ctx.accounts.vote_account.revealed = true;
+        emit!(VoteRevealed {
+            voter: ctx.accounts.signer.key(),
+            epoch,
+            amounts,
+        });
+        Ok(())
+    }
+    pub fn delegate(ctx: Context<Delegate>, delegate: Pubkey, commission_bps: u16) -> Result<()> {
+        if commission_bps as u64 > BPS_DENOMINATOR {
+            return Err(CustomError::InvalidCommission.into())
+        }
+        ctx.accounts.delegation.delegator = ctx.accounts.signer.key();
+        ctx.accounts.delegation.delegate = delegate;
+        ctx.accounts.delegation.commission_bps = commission_bps;
+        emit!(Delegated {
+            delegator: ctx.accounts.signer.key(),
+            delegate,
+            commission_bps,
+        });
+        Ok(())
+    }
+    pub fn undelegate(ctx: Context<Undelegate>) -> Result<()> {
+        emit!(Undelegated {
+            delegator: ctx.accounts.signer.key(),
+            delegate: ctx.accounts.delegation.delegate,
+        });
+        Ok(())
+    }
+    // votes with the delegator's voting power; the vote account stays the delegator's so claim pays them
+    pub fn delegate_vote(ctx: Context<DelegateVote>, epoch: u64, amounts: Vec<u64>, max_fee_lamports: u64) -> Result<()> {
+        if epoch != ctx.accounts.global_data_account.epoch {
+            return Err(CustomError::IncorrectEpochNum.into())
+        }
+        let time = Clock::get()?.unix_timestamp as u64;
+        if time >= ctx.accounts.global_data_account.voting_deadline() {
+            return Err(CustomError::EpochExpired.into())
+        }
+        if amounts.len() > ctx.accounts.epoch_account.fields.len() {
+            return Err(CustomError::InvalidFieldIndex.into())
+        }
+        if ctx.accounts.epoch_account.commit_reveal {
+            return Err(CustomError::CommitRevealRequired.into())
+        }
+        if ctx.accounts.vote_account.fields.is_empty() {
+            ctx.accounts.vote_account.fields = vec![0; ctx.accounts.epoch_account.fields.len()];
+            ctx.accounts.vote_account.delegated_fields = vec![0; ctx.accounts.epoch_account.fields.len()];
+        }
+        let first_vote = epoch != ctx.accounts.user_data_account.epoch;
+        let price = ctx.accounts.global_data_account.vote_price(ctx.accounts.epoch_account.voters, first_vote);
+        charge_vote_fee(&ctx.accounts.signer, &ctx.accounts.program_authority, &ctx.accounts.system_program, price, max_fee_lamports)?;
+        tally_vote(&mut ctx.accounts.epoch_account, &mut ctx.accounts.vote_account, &mut ctx.accounts.user_data_account, epoch, &amounts, first_vote)?;
+        for (i, amount) in amounts.iter().enumerate() {
+            ctx.accounts.vote_account.delegated_fields[i] += amount;
+        }
+        ctx.accounts.user_stats_account.active_reserve_epochs += 1;
+        ctx.accounts.vote_account.owner = ctx.accounts.delegator.key();
+        ctx.accounts.vote_account.delegate = ctx.accounts.signer.key();
+        ctx.accounts.vote_account.commission_bps = ctx.accounts.delegation.commission_bps;
+        emit!(DelegateVoted {
+            delegate: ctx.accounts.signer.key(),
+            delegator: ctx.accounts.delegator.key(),
+            epoch,
+            amounts,
+            fee_lamports: price,
+        });
         Ok(())
     }
     pub fn claim(ctx: Context<Claim>, epoch: u64) -> Result<()> {
         if epoch >= ctx.accounts.global_data_account.epoch {
             return Err(CustomError::IncorrectEpochNum.into())
         }
-        let base_amount = std::cmp::min(ctx.accounts.epoch_account.reward, ctx.accounts.program_holder_account.amount);
-        let reward = ctx.accounts.vote_account.fields[ctx.accounts.epoch_account.winner as usize] * base_amount / ctx.accounts.epoch_account.fields[ctx.accounts.epoch_account.winner as usize];
-        if reward > 0 && ctx.accounts.global_data_account.epoch <= epoch + 10 {
+        if ctx.accounts.global_data_account.epoch > epoch + ctx.accounts.global_data_account.claim_window_epochs {
+            return Err(CustomError::ClaimWindowExpired.into())
+        }
+        ctx.accounts.vote_account.claimed = true;
+        let reward = ctx.accounts.epoch_account.outcome.share(&ctx.accounts.epoch_account.fields, &ctx.accounts.vote_account.fields, ctx.accounts.epoch_account.reward);
+        if reward > 0 {
+            if ctx.accounts.epoch_account.total_claimed + reward > ctx.accounts.epoch_account.reward {
+                return Err(CustomError::RewardExceedsFunded.into())
+            }
+            if reward > ctx.accounts.program_holder_account.amount {
+                return Err(CustomError::InsufficientRewardBalance.into())
+            }
             ctx.accounts.user_stats_account.amount_claimed += reward;
-            transfer(
+            ctx.accounts.epoch_account.total_claimed += reward;
+            ctx.accounts.global_data_account.reserved -= reward;
+            // commission is only taken from the part of the reward earned by the delegate's votes
+            let delegated_reward = ctx.accounts.epoch_account.outcome.share(&ctx.accounts.epoch_account.fields, &ctx.accounts.vote_account.delegated_fields, ctx.accounts.epoch_account.reward);
+            let commission = (delegated_reward as u128 * ctx.accounts.vote_account.commission_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+            if commission > 0 {
+                let delegate_token_account = match &ctx.accounts.delegate_token_account {
+                    Some(account) if account.owner == ctx.accounts.vote_account.delegate => account,
+                    _ => return Err(CustomError::InvalidDelegateAccount.into()),
+                };
+                transfer_checked(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        TransferChecked {
+                            from: ctx.accounts.program_holder_account.to_account_info(),
+                            mint: ctx.accounts.ogc_mint.to_account_info(),
+                            to: delegate_token_account.to_account_info(),
+                            authority: ctx.accounts.program_authority.to_account_info()
+                        },
+                        &[&[b"auth", &[ctx.bumps.program_authority]]]
+                    ),
+                    commission,
+                    ctx.accounts.ogc_mint.decimals,
+                )?;
+            }
+            transfer_checked(
                 CpiContext::new_with_signer(
                     ctx.accounts.token_program.to_account_info(),
-                    Transfer {
+                    TransferChecked {
                         from: ctx.accounts.program_holder_account.to_account_info(),
+                        mint: ctx.accounts.ogc_mint.to_account_info(),
+                        to: ctx.accounts.signer_token_account.to_account_info(),
+                        authority: ctx.accounts.program_authority.to_account_info()
+                    },
+                    &[&[b"auth", &[ctx.bumps.program_authority]]]
+                ),
+                reward - commission,
+                ctx.accounts.ogc_mint.decimals,
+            )?;
+        }
+        emit!(Claimed {
+            owner: ctx.accounts.signer.key(),
+            epoch,
+            amount: reward,
+        });
+        Ok(())
+    }
+    // each voter claims each pool once, tracked by a receipt so pools can be claimed in any number of transactions
+    pub fn claim_pool(ctx: Context<ClaimPool>, epoch: u64) -> Result<()> {
+        if epoch >= ctx.accounts.global_data_account.epoch {
+            return Err(CustomError::IncorrectEpochNum.into())
+        }
+        if ctx.accounts.global_data_account.epoch > epoch + ctx.accounts.global_data_account.claim_window_epochs {
+            return Err(CustomError::ClaimWindowExpired.into())
+        }
+        let share = ctx.accounts.epoch_account.outcome.share(&ctx.accounts.epoch_account.fields, &ctx.accounts.vote_account.fields, ctx.accounts.reward_pool.amount);
+        if ctx.accounts.reward_pool.total_claimed + share > ctx.accounts.reward_pool.amount {
+            return Err(CustomError::RewardExceedsFunded.into())
+        }
+        ctx.accounts.reward_pool.total_claimed += share;
+        ctx.accounts.pool_receipt.owner = ctx.accounts.signer.key();
+        ctx.accounts.pool_receipt.epoch = epoch;
+        ctx.accounts.pool_receipt.amount = share;
+        if share > 0 {
+            transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.reward_pool_vault.to_account_info(),
+                        mint: ctx.accounts.mint.to_account_info(),
                         to: ctx.accounts.signer_token_account.to_account_info(),
                         authority: ctx.accounts.program_authority.to_account_info()
                     },
                     &[&[b"auth", &[ctx.bumps.program_authority]]]
                 ),
-                reward,
+                share,
+                ctx.accounts.mint.decimals,
+            )?;
+        }
+        emit!(PoolRewardClaimed {
+            owner: ctx.accounts.signer.key(),
+            epoch,
+            mint: ctx.accounts.mint.key(),
+            amount: share,
+        });
+        Ok(())
+    }
+    // remaining accounts are the signer's pool receipts for the epoch, closed along with the vote account
+    pub fn close_vote_account<'info>(ctx: Context<'_, '_, 'info, 'info, CloseVoteAccount<'info>>, epoch: u64) -> Result<()> {
+        for account in ctx.remaining_accounts {
+            let pool_receipt: Account<PoolReceipt> = Account::try_from(account)?;
+            if pool_receipt.owner != ctx.accounts.signer.key() || pool_receipt.epoch != epoch {
+                return Err(CustomError::InvalidPoolReceipt.into())
+            }
+            pool_receipt.close(ctx.accounts.signer.to_account_info())?;
+        }
+        emit!(VoteAccountClosed {
+            owner: ctx.accounts.signer.key(),
+            epoch,
+        });
+        Ok(())
+    }
+    // rolls what is left of a pool into the same mint's pool for the current epoch, like sweep_epoch does for the base reward.
+    // epochs without a winner pay nobody, so their pools can be swept as soon as the epoch is over
+    pub fn sweep_pool(ctx: Context<SweepPool>, epoch: u64) -> Result<()> {
+        let no_winner = ctx.accounts.epoch_account.outcome == EpochOutcome::NoWinner;
+        if epoch >= ctx.accounts.global_data_account.epoch || (!no_winner && ctx.accounts.global_data_account.epoch <= epoch + ctx.accounts.global_data_account.claim_window_epochs) {
+            return Err(CustomError::ClaimWindowOpen.into())
+        }
+        if ctx.accounts.next_reward_pool.vault == Pubkey::default() {
+            ctx.accounts.next_reward_pool.epoch = ctx.accounts.global_data_account.epoch;
+            ctx.accounts.next_reward_pool.mint = ctx.accounts.mint.key();
+            ctx.accounts.next_reward_pool.vault = ctx.accounts.next_reward_pool_vault.key();
+        }
+        let remainder = ctx.accounts.reward_pool.amount - ctx.accounts.reward_pool.total_claimed;
+        ctx.accounts.reward_pool.swept = true;
+        let balance_before = ctx.accounts.next_reward_pool_vault.amount;
+        if remainder > 0 {
+            transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.reward_pool_vault.to_account_info(),
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to: ctx.accounts.next_reward_pool_vault.to_account_info(),
+                        authority: ctx.accounts.program_authority.to_account_info()
+                    },
+                    &[&[b"auth", &[ctx.bumps.program_authority]]]
+                ),
+                remainder,
+                ctx.accounts.mint.decimals,
             )?;
         }
+        ctx.accounts.next_reward_pool_vault.reload()?;
+        let amount = ctx.accounts.next_reward_pool_vault.amount - balance_before;
+        ctx.accounts.next_reward_pool.amount += amount;
+        emit!(PoolSwept {
+            epoch,
+            mint: ctx.accounts.mint.key(),
+            amount,
+            rollover_epoch: ctx.accounts.global_data_account.epoch,
+        });
+        Ok(())
+    }
+    pub fn sweep_epoch(ctx: Context<SweepEpoch>, epoch: u64) -> Result<()> {
+        let remainder = ctx.accounts.epoch_account.reward.saturating_sub(ctx.accounts.epoch_account.total_claimed);
+        ctx.accounts.global_data_account.rollover += remainder;
+        ctx.accounts.global_data_account.reserved = ctx.accounts.global_data_account.reserved.saturating_sub(remainder);
+        ctx.accounts.epoch_account.swept = true;
+        emit!(EpochSwept {
+            epoch,
+            amount: remainder,
+        });
         Ok(())
     }
 }
@@ -240,53 +1034,513 @@ pub enum CustomError {
     #[msg("Invalid signer")]
     InvalidSigner,
     #[msg("No fees to withdraw")]
-    NoFeesToWithdraw
+    NoFeesToWithdraw,
+    #[msg("Invalid number of fields")]
+    InvalidNumFields,
+    #[msg("Invalid field index")]
+    InvalidFieldIndex,
+    #[msg("Invalid winner rule")]
+    InvalidWinnerRule,
+    #[msg("Claim window still open")]
+    ClaimWindowOpen,
+    #[msg("Epoch already swept")]
+    EpochAlreadySwept,
+    #[msg("Reward exceeds funded amount")]
+    RewardExceedsFunded,
+    #[msg("Insufficient reward balance")]
+    InsufficientRewardBalance,
+    #[msg("Exceeds unreserved balance")]
+    ExceedsUnreservedBalance,
+    #[msg("Claim window expired")]
+    ClaimWindowExpired,
+    #[msg("Invalid claim window")]
+    InvalidClaimWindow,
+    #[msg("Program paused")]
+    ProgramPaused,
+    #[msg("Fee exceeds max")]
+    FeeExceedsMax,
+    #[msg("Invalid reveal length")]
+    InvalidRevealLength,
+    #[msg("Commit reveal required")]
+    CommitRevealRequired,
+    #[msg("Commit reveal disabled")]
+    CommitRevealDisabled,
+    #[msg("Commit phase over")]
+    CommitPhaseOver,
+    #[msg("Not reveal phase")]
+    NotRevealPhase,
+    #[msg("Already committed")]
+    AlreadyCommitted,
+    #[msg("Already revealed")]
+    AlreadyRevealed,
+    #[msg("Invalid reveal")]
+    InvalidReveal,
+    #[msg("Commit reveal votes are final")]
+    CommitRevealVotesFinal,
+    #[msg("Invalid lock tiers")]
+    InvalidLockTiers,
+    #[msg("Invalid lock tier")]
+    InvalidLockTier,
+    #[msg("Invalid penalty")]
+    InvalidPenalty,
+    #[msg("Early unlock disabled")]
+    EarlyUnlockDisabled,
+    #[msg("Invalid lock extension")]
+    InvalidLockExtension,
+    #[msg("Invalid lock merge")]
+    InvalidLockMerge,
+    #[msg("Invalid commission")]
+    InvalidCommission,
+    #[msg("Invalid delegate")]
+    InvalidDelegate,
+    #[msg("Invalid delegate account")]
+    InvalidDelegateAccount,
+    #[msg("Invalid voting cutoff")]
+    InvalidVotingCutoff,
+    #[msg("Already claimed")]
+    AlreadyClaimed,
+    #[msg("Reward pool already swept")]
+    PoolAlreadySwept,
+    #[msg("Invalid emission schedule")]
+    InvalidEmissionSchedule,
+    #[msg("Invalid program data account")]
+    InvalidProgramData,
+    #[msg("Invalid pause flags")]
+    InvalidPauseFlags,
+    #[msg("Invalid pool receipt")]
+    InvalidPoolReceipt,
 }
 
-#[account]
-pub struct GlobalDataAccount {
-    pub epoch: u64,
-    pub epoch_end_time: u64,
-    pub epoch_lock_time: u64,
+#[event]
+pub struct AdminProposed {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+}
+#[event]
+pub struct AdminAccepted {
+    pub admin: Pubkey,
+}
+#[event]
+pub struct ConfigChanged {
     pub epoch_length: u64,
     pub reward_amount: u64,
-    pub ogc_mint: Pubkey,
-    pub ogg_mint: Pubkey,
+    pub num_fields: u8,
+    pub winner_rule: WinnerRule,
+    pub claim_window_epochs: u64,
+}
+#[event]
+pub struct VotePricingChanged {
     pub fee_lamports: u64,
+    pub pricing_curve: PricingCurve,
+    pub top_up_fee_lamports: u64,
 }
-#[account]
-pub struct EpochAccount {
-    pub fields: [u64; 4],
-    pub winner: u64,
+#[event]
+pub struct CommitRevealChanged {
+    pub commit_reveal: bool,
+    pub reveal_length: u64,
+}
+#[event]
+pub struct LockTiersChanged {
+    pub lock_tiers: Vec<LockTier>,
+}
+#[event]
+pub struct EmissionScheduleChanged {
+    pub emission_schedule: Option<EmissionSchedule>,
+}
+#[event]
+pub struct EarlyUnlockChanged {
+    pub enabled: bool,
+    pub penalty_bps: u16,
+    pub linear_decay: bool,
+    pub burn_penalty: bool,
+}
+#[event]
+pub struct PauseChanged {
+    pub paused_flags: u8,
+}
+#[event]
+pub struct VotingCutoffChanged {
+    pub voting_cutoff: u64,
+}
+#[event]
+pub struct RewardsDeposited {
+    pub depositor: Pubkey,
+    pub amount: u64,
+}
+#[event]
+pub struct PoolRewardsDeposited {
+    pub depositor: Pubkey,
+    pub epoch: u64,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+#[event]
+pub struct RewardsWithdrawn {
+    pub admin: Pubkey,
+    pub amount: u64,
+}
+#[event]
+pub struct FeesWithdrawn {
+    pub admin: Pubkey,
+    pub lamports: u64,
+}
+#[event]
+pub struct EpochAdvanced {
+    pub epoch: u64,
+    pub outcome: EpochOutcome,
+    pub fields: Vec<u64>,
     pub reward: u64,
-    pub voters: u64,
+    pub emission: u64,
+    pub rollover: u64,
+    pub epoch_end_time: u64,
 }
-#[derive(Accounts)]
-pub struct InitializeFirstEpochAccount<'info> {
-    #[account(mut)]
-    pub signer: Signer<'info>,
-    #[account(
-        init,
-        seeds = [b"epoch", 0_u64.to_le_bytes().as_ref()],
-        bump,
-        payer = signer,
-        space = 8 + 8 + 8 + 8 + 8 * 4
-    )]
-    pub first_epoch_account: Account<'info, EpochAccount>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct Locked {
+    pub owner: Pubkey,
+    pub epoch: u64,
+    pub amount: u64,
+    pub unlock_epoch: u64,
+    pub tier: u8,
+    pub power: u64,
 }
-// solana program close 6B4ASnWa6CdF7tWC8vRfdrpU1PdDuDVxWyieLC5tqUjN --bypass-warning --keypair /home/xeony/.config/solana/id.json --url devnet
-#[derive(Accounts)]
-pub struct Initialize<'info> {
+#[event]
+pub struct Unlocked {
+    pub owner: Pubkey,
+    pub epoch: u64,
+    pub amount: u64,
+}
+#[event]
+pub struct EarlyUnlocked {
+    pub owner: Pubkey,
+    pub epoch: u64,
+    pub amount: u64,
+    pub penalty: u64,
+    pub burned: bool,
+}
+#[event]
+pub struct LockExtended {
+    pub owner: Pubkey,
+    pub epoch: u64,
+    pub unlock_epoch: u64,
+    pub tier: u8,
+    pub power: u64,
+}
+#[event]
+pub struct LockSettled {
+    pub owner: Pubkey,
+    pub epoch: u64,
+    pub power: u64,
+}
+#[event]
+pub struct LocksMerged {
+    pub owner: Pubkey,
+    pub epoch: u64,
+    pub source_epoch: u64,
+    pub amount: u64,
+    pub unlock_epoch: u64,
+    pub power: u64,
+}
+#[event]
+pub struct LockSplit {
+    pub owner: Pubkey,
+    pub epoch: u64,
+    pub split_epoch: u64,
+    pub amount: u64,
+}
+#[event]
+pub struct Voted {
+    pub voter: Pubkey,
+    pub epoch: u64,
+    pub amounts: Vec<u64>,
+    pub fee_lamports: u64,
+}
+#[event]
+pub struct Revoted {
+    pub voter: Pubkey,
+    pub epoch: u64,
+    pub amounts: Vec<u64>,
+    pub fee_lamports: u64,
+}
+#[event]
+pub struct VoteCommitted {
+    pub voter: Pubkey,
+    pub epoch: u64,
+    pub reserved: u64,
+    pub fee_lamports: u64,
+}
+#[event]
+pub struct VoteRevealed {
+    pub voter: Pubkey,
+    pub epoch: u64,
+    pub amounts: Vec<u64>,
+}
+#[event]
+pub struct Delegated {
+    pub delegator: Pubkey,
+    pub delegate: Pubkey,
+    pub commission_bps: u16,
+}
+#[event]
+pub struct Undelegated {
+    pub delegator: Pubkey,
+    pub delegate: Pubkey,
+}
+#[event]
+pub struct DelegateVoted {
+    pub delegate: Pubkey,
+    pub delegator: Pubkey,
+    pub epoch: u64,
+    pub amounts: Vec<u64>,
+    pub fee_lamports: u64,
+}
+#[event]
+pub struct Claimed {
+    pub owner: Pubkey,
+    pub epoch: u64,
+    pub amount: u64,
+}
+#[event]
+pub struct PoolRewardClaimed {
+    pub owner: Pubkey,
+    pub epoch: u64,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+#[event]
+pub struct VoteAccountClosed {
+    pub owner: Pubkey,
+    pub epoch: u64,
+}
+#[event]
+pub struct PoolSwept {
+    pub epoch: u64,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub rollover_epoch: u64,
+}
+#[event]
+pub struct EpochSwept {
+    pub epoch: u64,
+    pub amount: u64,
+}
+#[account]
+pub struct GlobalDataAccount {
+    pub epoch: u64,
+    pub epoch_end_time: u64,
+    pub epoch_length: u64,
+    pub reward_amount: u64,
+    // reward token, held in program_holder_account and paid out by claim
+    pub ogc_mint: Pubkey,
+    // staking token, locked in each user's holder account for voting power
+    pub ogg_mint: Pubkey,
+    pub fee_lamports: u64,
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub num_fields: u8,
+    pub winner_rule: WinnerRule,
+    pub rollover: u64,
+    pub reserved: u64,
+    pub claim_window_epochs: u64,
+    pub paused_flags: u8,
+    pub pricing_curve: PricingCurve,
+    pub top_up_fee_lamports: u64,
+    pub commit_reveal: bool,
+    pub reveal_length: u64,
+    pub lock_tiers: Vec<LockTier>,
+    pub early_unlock_enabled: bool,
+    pub early_unlock_penalty_bps: u16,
+    pub early_unlock_linear_decay: bool,
+    pub early_unlock_burn: bool,
+    pub voting_cutoff: u64,
+    pub emission_schedule: Option<EmissionSchedule>,
+    pub total_emitted: u64,
+    // scheduled emission carried in the rollover, added to total_emitted once it is funded
+    pub rollover_emission: u64,
+}
+impl GlobalDataAccount {
+    // voting closes voting_cutoff seconds before the epoch ends
+    pub fn voting_deadline(&self) -> u64 {
+        self.epoch_end_time.saturating_sub(self.voting_cutoff)
+    }
+    // with linear decay the penalty shrinks with the share of the lock period still remaining
+    pub fn early_unlock_penalty(&self, lock_account: &LockAccount, amount: u64) -> u64 {
+        let remaining = lock_account.unlock_epoch.saturating_sub(self.epoch);
+        if remaining == 0 {
+            return 0
+        }
+        let mut penalty = amount as u128 * self.early_unlock_penalty_bps as u128 / BPS_DENOMINATOR as u128;
+        let duration = lock_account.unlock_epoch.saturating_sub(lock_account.start_epoch);
+        if self.early_unlock_linear_decay && duration > remaining {
+            penalty = penalty * remaining as u128 / duration as u128;
+        }
+        penalty as u64
+    }
+    // the curve only prices a user's first vote in an epoch, later top-ups pay the flat top-up fee
+    pub fn vote_price(&self, voters: u64, first_vote: bool) -> u64 {
+        if first_vote {
+            self.pricing_curve.price(self.fee_lamports, voters)
+        } else {
+            self.top_up_fee_lamports
+        }
+    }
+}
+#[account]
+pub struct EpochAccount {
+    pub fields: Vec<u64>,
+    pub reward: u64,
+    pub voters: u64,
+    pub winner_rule: WinnerRule,
+    pub outcome: EpochOutcome,
+    pub total_claimed: u64,
+    pub swept: bool,
+    pub commit_reveal: bool,
+    pub reveal_start_time: u64,
+}
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum WinnerRule {
+    HighestWins,
+    SecondHighestWins,
+    LowestNonZeroWins,
+    // 1-indexed rank, so Nth(1) is the highest field
+    Nth(u8),
+}
+impl WinnerRule {
+    // fields nobody voted for are never picked, and every field sharing the
+    // picked field's total is part of the result
+    pub fn select(&self, fields: &[u64]) -> EpochOutcome {
+        let mut ranked: Vec<usize> = (0..fields.len()).filter(|&i| fields[i] > 0).collect();
+        ranked.sort_by(|&a, &b| fields[b].cmp(&fields[a]));
+        let rank = match *self {
+            WinnerRule::HighestWins => 1,
+            WinnerRule::SecondHighestWins => 2,
+            WinnerRule::LowestNonZeroWins => ranked.len(),
+            WinnerRule::Nth(k) => k as usize,
+        };
+        let Some(&index) = rank.checked_sub(1).and_then(|r| ranked.get(r)) else {
+            return EpochOutcome::NoWinner
+        };
+        let tied: Vec<u8> = (0..fields.len()).filter(|&i| fields[i] == fields[index]).map(|i| i as u8).collect();
+        if tied.len() == 1 {
+            EpochOutcome::Winner(index as u8)
+        } else {
+            EpochOutcome::Tie(tied)
+        }
+    }
+}
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct LockTier {
+    pub lock_epochs: u64,
+    // 10000 counts each locked token once
+    pub multiplier_bps: u16,
+}
+impl LockTier {
+    pub fn voting_power(&self, amount: u64) -> u64 {
+        (amount as u128 * self.multiplier_bps as u128 / BPS_DENOMINATOR as u128) as u64
+    }
+}
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct EmissionSchedule {
+    pub enabled: bool,
+    pub start_epoch: u64,
+    pub initial_amount: u64,
+    // epochs paid the full initial_amount before the first halving
+    pub initial_epochs: u64,
+    pub halving_epochs: u64,
+    // hard cap on the global total_emitted
+    pub total_cap: u64,
+}
+impl EmissionSchedule {
+    pub fn emission(&self, epoch: u64, total_emitted: u64) -> u64 {
+        let elapsed = epoch.saturating_sub(self.start_epoch);
+        let amount = if elapsed < self.initial_epochs {
+            self.initial_amount
+        } else {
+            let halvings = 1 + (elapsed - self.initial_epochs) / self.halving_epochs;
+            if halvings >= u64::BITS as u64 { 0 } else { self.initial_amount >> halvings }
+        };
+        std::cmp::min(amount, self.total_cap.saturating_sub(total_emitted))
+    }
+}
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PricingCurve {
+    Flat,
+    Linear,
+    Quadratic,
+    // the u64 is the maximum price in lamports
+    LinearCapped(u64),
+    QuadraticCapped(u64),
+}
+impl PricingCurve {
+    // price for the next voter to join an epoch that already has `voters` voters,
+    // clients can also simulate get_vote_price to read it for a given epoch and voter
+    pub fn price(&self, fee_lamports: u64, voters: u64) -> u64 {
+        match *self {
+            PricingCurve::Flat => fee_lamports,
+            PricingCurve::Linear => fee_lamports.saturating_mul(voters),
+            PricingCurve::Quadratic => fee_lamports.saturating_mul(voters.saturating_pow(2)),
+            PricingCurve::LinearCapped(cap) => std::cmp::min(fee_lamports.saturating_mul(voters), cap),
+            PricingCurve::QuadraticCapped(cap) => std::cmp::min(fee_lamports.saturating_mul(voters.saturating_pow(2)), cap),
+        }
+    }
+}
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum EpochOutcome {
+    NoWinner,
+    Winner(u8),
+    // reward is split evenly between the tied fields
+    Tie(Vec<u8>),
+}
+impl EpochOutcome {
+    pub fn share(&self, epoch_fields: &[u64], vote_fields: &[u64], reward: u64) -> u64 {
+        let field_share = |i: u8, reward: u64| {
+            let i = i as usize;
+            (vote_fields[i] as u128 * reward as u128 / epoch_fields[i] as u128) as u64
+        };
+        match self {
+            EpochOutcome::NoWinner => 0,
+            EpochOutcome::Winner(i) => field_share(*i, reward),
+            EpochOutcome::Tie(tied) => {
+                let split = reward / tied.len() as u64;
+                tied.iter().map(|&i| field_share(i, split)).sum()
+            }
+        }
+    }
+}
+#[derive(Accounts)]
+pub struct InitializeFirstEpochAccount<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"epoch", 0_u64.to_le_bytes().as_ref()],
+        bump,
+        payer = signer,
+        space = 8 + 8 + 8 + 4 + 8 * DEFAULT_NUM_FIELDS as usize + 2 + 1 + 4 + DEFAULT_NUM_FIELDS as usize + 8 + 1 + 1 + 8
+    )]
+    pub first_epoch_account: Account<'info, EpochAccount>,
+    pub system_program: Program<'info, System>,
+}
+// solana program close 6B4ASnWa6CdF7tWC8vRfdrpU1PdDuDVxWyieLC5tqUjN --bypass-warning --keypair /home/xeony/.config/solana/id.json --url devnet
+#[derive(Accounts)]
+pub struct Initialize<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
-    pub ogc_mint: Account<'info, Mint>,
-    pub ogg_mint: Account<'info, Mint>,
+    // only the upgrade authority can initialize, so the first admin can't be front-run
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key()) @ CustomError::InvalidProgramData
+    )]
+    pub program: Program<'info, crate::program::OgcReserve>,
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(signer.key()) @ CustomError::InvalidSigner
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub ogc_mint: InterfaceAccount<'info, Mint>,
+    pub ogg_mint: InterfaceAccount<'info, Mint>,
     #[account(
         init,
         seeds = [b"global"],
         bump,
-        space = 8 + 8 + 8 + 8 + 8 + 8 + 32 + 32 + 8,
+        space = 8 + 8 + 8 + 8 + 8 + 32 + 32 + 8 + 32 + 32 + 1 + 2 + 8 + 8 + 8 + 1 + 9 + 8 + 1 + 8 + 4 + 10 * MAX_LOCK_TIERS + 1 + 2 + 1 + 1 + 8 + 1 + 41 + 8 + 8,
         payer = signer,
     )]
     pub global_data_account: Box<Account<'info, GlobalDataAccount>>,
@@ -298,7 +1552,7 @@ pub struct Initialize<'info> {
         token::authority = program_authority,
         payer = signer,
     )]
-    pub program_holder_account: Account<'info, TokenAccount>,
+    pub program_holder_account: InterfaceAccount<'info, TokenAccount>,
     #[account(
         init,
         seeds = [b"auth"],
@@ -309,12 +1563,12 @@ pub struct Initialize<'info> {
     /// CHECK: 
     pub program_authority: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 #[derive(Accounts)]
 pub struct ModifyGlobalData<'info> {
     #[account(
-        constraint = signer.key() == ADMIN.parse::<Pubkey>().unwrap() @ CustomError::InvalidSigner
+        constraint = signer.key() == global_data_account.admin @ CustomError::InvalidSigner
     )]
     pub signer: Signer<'info>,
     #[account(
@@ -325,243 +1579,947 @@ pub struct ModifyGlobalData<'info> {
     pub global_data_account: Account<'info, GlobalDataAccount>,
 }
 #[derive(Accounts)]
-pub struct DepositOgg<'info> {
+pub struct ProposeAdmin<'info> {
+    #[account(
+        constraint = signer.key() == global_data_account.admin @ CustomError::InvalidSigner
+    )]
     pub signer: Signer<'info>,
-    #[account(mut)]
-    pub signer_token_account: Account<'info, TokenAccount>,
     #[account(
         mut,
-        seeds = [b"holder"],
+        seeds = [b"global"],
         bump,
     )]
-    pub program_holder_account: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+    pub global_data_account: Account<'info, GlobalDataAccount>,
 }
 #[derive(Accounts)]
-pub struct WithdrawOgg<'info> {
+pub struct AcceptAdmin<'info> {
+    #[account(
+        constraint = new_admin.key() == global_data_account.pending_admin @ CustomError::InvalidSigner
+    )]
+    pub new_admin: Signer<'info>,
     #[account(
-        constraint = signer.key() == ADMIN.parse::<Pubkey>().unwrap() @ CustomError::InvalidSigner
+        mut,
+        seeds = [b"global"],
+        bump,
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+}
+#[derive(Accounts)]
+pub struct SetVotePricing<'info> {
+    #[account(
+        constraint = signer.key() == global_data_account.admin @ CustomError::InvalidSigner
     )]
     pub signer: Signer<'info>,
-    #[account(mut)]
-    pub signer_token_account: Account<'info, TokenAccount>,
     #[account(
         mut,
-        seeds = [b"holder"],
+        seeds = [b"global"],
         bump,
     )]
-    pub program_holder_account: Account<'info, TokenAccount>,
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+}
+#[derive(Accounts)]
+pub struct SetCommitReveal<'info> {
     #[account(
-        seeds = [b"auth"],
+        constraint = signer.key() == global_data_account.admin @ CustomError::InvalidSigner
+    )]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"global"],
         bump,
     )]
-    /// CHECK: 
-    pub program_authority: AccountInfo<'info>,
-    pub token_program: Program<'info, Token>,
+    pub global_data_account: Account<'info, GlobalDataAccount>,
 }
 #[derive(Accounts)]
-pub struct WithdrawSol<'info> {
+pub struct SetLockTiers<'info> {
+    #[account(
+        constraint = signer.key() == global_data_account.admin @ CustomError::InvalidSigner
+    )]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"global"],
+        bump,
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+}
+#[derive(Accounts)]
+pub struct SetEmissionSchedule<'info> {
+    #[account(
+        constraint = signer.key() == global_data_account.admin @ CustomError::InvalidSigner
+    )]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"global"],
+        bump,
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+}
+#[derive(Accounts)]
+pub struct SetEarlyUnlock<'info> {
+    #[account(
+        constraint = signer.key() == global_data_account.admin @ CustomError::InvalidSigner
+    )]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"global"],
+        bump,
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+}
+#[derive(Accounts)]
+pub struct SetPause<'info> {
+    #[account(
+        constraint = signer.key() == global_data_account.admin @ CustomError::InvalidSigner
+    )]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"global"],
+        bump,
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+}
+#[derive(Accounts)]
+pub struct SetVotingCutoff<'info> {
+    #[account(
+        constraint = signer.key() == global_data_account.admin @ CustomError::InvalidSigner
+    )]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"global"],
+        bump,
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+}
+#[derive(Accounts)]
+pub struct DepositOgg<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"global"],
+        bump,
+        has_one = ogc_mint @ CustomError::InvalidMintAccount
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+    pub ogc_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = global_data_account.ogc_mint,
+        token::authority = signer,
+    )]
+    pub signer_token_account: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
-        constraint = signer.key() == ADMIN.parse::<Pubkey>().unwrap() @ CustomError::InvalidSigner
+        seeds = [b"holder"],
+        bump,
+        token::mint = global_data_account.ogc_mint,
     )]
+    pub program_holder_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+#[account]
+pub struct RewardPool {
+    pub epoch: u64,
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub amount: u64,
+    pub total_claimed: u64,
+    pub swept: bool,
+}
+#[account]
+pub struct PoolReceipt {
+    pub owner: Pubkey,
+    pub epoch: u64,
+    pub amount: u64,
+}
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct DepositReward<'info> {
+    #[account(mut)]
     pub signer: Signer<'info>,
     #[account(
         mut,
+        token::mint = mint,
+        token::authority = signer,
+    )]
+    pub signer_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        seeds = [b"pool", epoch.to_le_bytes().as_ref(), mint.key().as_ref()],
+        bump,
+        payer = signer,
+        space = 8 + 8 + 32 + 32 + 8 + 8 + 1,
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+    #[account(
+        init_if_needed,
+        seeds = [b"pool_vault", reward_pool.key().as_ref()],
+        bump,
+        payer = signer,
+        token::mint = mint,
+        token::authority = program_authority,
+    )]
+    pub reward_pool_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [b"global"],
+        bump,
+        constraint = epoch >= global_data_account.epoch @ CustomError::IncorrectEpochNum
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+    #[account(
         seeds = [b"auth"],
         bump,
     )]
     /// CHECK: 
     pub program_authority: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 #[derive(Accounts)]
-#[instruction(epoch: u64)]
-pub struct NewEpoch<'info> {
-    #[account(mut)]
+pub struct WithdrawOgg<'info> {
+    #[account(
+        constraint = signer.key() == global_data_account.admin @ CustomError::InvalidSigner
+    )]
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"global"],
+        bump,
+        has_one = ogc_mint @ CustomError::InvalidMintAccount
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+    pub ogc_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = global_data_account.ogc_mint,
+        token::authority = signer,
+    )]
+    pub signer_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"holder"],
+        bump,
+        token::mint = global_data_account.ogc_mint,
+    )]
+    pub program_holder_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [b"auth"],
+        bump,
+    )]
+    /// CHECK: 
+    pub program_authority: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+#[derive(Accounts)]
+pub struct WithdrawSol<'info> {
+    #[account(
+        mut,
+        constraint = signer.key() == global_data_account.admin @ CustomError::InvalidSigner
+    )]
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"global"],
+        bump,
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+    #[account(
+        mut,
+        seeds = [b"auth"],
+        bump,
+    )]
+    /// CHECK: 
+    pub program_authority: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct NewEpoch<'info> {
+    #[account(mut)]
     pub signer: Signer<'info>,
     #[account(
         init,
         seeds = [b"epoch", epoch.to_le_bytes().as_ref()],
         bump,
-        payer = signer,
-        space = 8 + 8 + 8 + 8 + 8 * 4,
-        constraint = global_data_account.epoch + 1 == epoch @ CustomError::IncorrectEpochNum
+        payer = signer,
+        space = 8 + 8 + 8 + 4 + 8 * global_data_account.num_fields as usize + 2 + 1 + 4 + global_data_account.num_fields as usize + 8 + 1 + 1 + 8,
+        constraint = global_data_account.epoch + 1 == epoch @ CustomError::IncorrectEpochNum
+    )]
+    pub epoch_account: Account<'info, EpochAccount>,
+    #[account(
+        mut,
+        seeds = [b"epoch", (epoch - 1).to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub prev_epoch_account: Account<'info, EpochAccount>,
+    #[account(
+        mut,
+        seeds = [b"global"],
+        bump,    
+        constraint = global_data_account.paused_flags & PAUSE_NEW_EPOCH == 0 @ CustomError::ProgramPaused
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+    #[account(
+        mut,
+        seeds = [b"holder"],
+        bump,
+        token::mint = global_data_account.ogc_mint,
+    )]
+    pub program_holder_account: InterfaceAccount<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct LockAccount {
+    pub unlock_epoch: u64,
+    pub epoch: u64,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub tier: u8,
+    pub multiplier_bps: u16,
+    pub power: u64,
+    pub start_epoch: u64,
+}
+impl LockAccount {
+    // voting power backed by `amount` of this lock, taking all of it once the lock is emptied
+    pub fn power_for(&self, amount: u64) -> u64 {
+        if amount >= self.amount {
+            return self.power
+        }
+        std::cmp::min((amount as u128 * self.multiplier_bps as u128 / BPS_DENOMINATOR as u128) as u64, self.power)
+    }
+}
+#[account]
+pub struct UserDataAccount {
+    pub amount: u64,
+    pub staked: u64,
+    pub epoch: u64,
+    pub voting_power: u64,
+    pub snapshot_epoch: u64,
+    pub snapshot_power: u64,
+}
+impl UserDataAccount {
+    // records the power held at the start of the epoch, before it first changes
+    pub fn checkpoint(&mut self, epoch: u64) {
+        if self.snapshot_epoch != epoch {
+            self.snapshot_epoch = epoch;
+            self.snapshot_power = self.voting_power;
+        }
+    }
+    // power added during an epoch only counts from the next one
+    pub fn epoch_power(&self, epoch: u64) -> u64 {
+        if self.snapshot_epoch == epoch {
+            std::cmp::min(self.snapshot_power, self.voting_power)
+        } else {
+            self.voting_power
+        }
+    }
+}
+#[account]
+pub struct UserStatsAccount {
+    pub owner: Pubkey,
+    pub amount_claimed: u64,
+    pub active_reserve_epochs: u64
+}
+#[derive(Accounts)]
+pub struct CreateStatsAccount<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"stats", signer.key().as_ref()],
+        bump,
+        payer = signer,
+        space = 8 + 8 + 8 + 32
+    )]
+    pub user_stats_account: Account<'info, UserStatsAccount>,
+    pub system_program: Program<'info, System>,
+}
+#[derive(Accounts)]
+pub struct CreateDataAccount<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"data", signer.key().as_ref()],
+        bump,
+        payer = signer,
+        space = 8 + 8 + 8 + 8 + 8 + 8 + 8,
+    )]
+    pub user_data_account: Account<'info, UserDataAccount>,
+    #[account(
+        constraint = mint.key() == global_data_account.ogg_mint @ CustomError::InvalidMintAccount
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        seeds = [b"holder", signer.key().as_ref()],
+        bump,
+        payer = signer,
+        token::mint = mint,
+        token::authority = program_authority,
+    )]
+    pub signer_holder_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [b"global"],
+        bump,
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+    #[account(
+        seeds = [b"auth"],
+        bump,
+    )]
+    /// CHECK: 
+    pub program_authority: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct CreateLockAccount<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"lock", signer.key().as_ref(), epoch.to_le_bytes().as_ref()],
+        bump,
+        payer = signer,
+        space = 8 + 8 + 8 + 8 + 32 + 1 + 2 + 8 + 8,
+    )]
+    pub lock_account: Account<'info, LockAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct Lock<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = global_data_account.ogg_mint,
+        token::authority = signer,
+    )]
+    pub signer_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"holder", signer.key().as_ref()],
+        bump,
+        token::mint = global_data_account.ogg_mint,
+    )]
+    pub signer_holder_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"lock", signer.key().as_ref(), epoch.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub lock_account: Account<'info, LockAccount>,
+    #[account(
+        mut,
+        seeds = [b"data", signer.key().as_ref()],
+        bump,
+    )]
+    pub user_data_account: Account<'info, UserDataAccount>,
+    #[account(
+        seeds = [b"global"],
+        bump,
+        constraint = epoch == global_data_account.epoch @ CustomError::IncorrectEpochNum,
+        constraint = global_data_account.paused_flags & PAUSE_LOCK == 0 @ CustomError::ProgramPaused,
+        has_one = ogg_mint @ CustomError::InvalidMintAccount
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+    pub ogg_mint: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct Unlock<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = global_data_account.ogg_mint,
+        token::authority = signer,
+    )]
+    pub signer_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"holder", signer.key().as_ref()],
+        bump,
+        token::mint = global_data_account.ogg_mint,
+    )]
+    pub signer_holder_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"lock", signer.key().as_ref(), epoch.to_le_bytes().as_ref()],
+        bump,
+        constraint = lock_account.unlock_epoch <= global_data_account.epoch @ CustomError::AccountNotUnlocked
+    )]
+    pub lock_account: Account<'info, LockAccount>,
+    #[account(
+        mut,
+        seeds = [b"data", signer.key().as_ref()],
+        bump,
+    )]
+    pub user_data_account: Account<'info, UserDataAccount>,
+    #[account(
+        seeds = [b"global"],
+        bump,
+        constraint = global_data_account.paused_flags & PAUSE_UNLOCK == 0 @ CustomError::ProgramPaused,
+        has_one = ogg_mint @ CustomError::InvalidMintAccount
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+    pub ogg_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        seeds = [b"auth"],
+        bump,
+    )]
+    /// CHECK: 
+    pub program_authority: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct ExtendLock<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"lock", signer.key().as_ref(), epoch.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub lock_account: Account<'info, LockAccount>,
+    #[account(
+        mut,
+        seeds = [b"data", signer.key().as_ref()],
+        bump,
+    )]
+    pub user_data_account: Account<'info, UserDataAccount>,
+    #[account(
+        seeds = [b"global"],
+        bump,
+        constraint = global_data_account.paused_flags & PAUSE_LOCK == 0 @ CustomError::ProgramPaused
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+}
+#[derive(Accounts)]
+#[instruction(owner: Pubkey, epoch: u64)]
+pub struct SettleLock<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"lock", owner.as_ref(), epoch.to_le_bytes().as_ref()],
+        bump,
+        constraint = lock_account.unlock_epoch <= global_data_account.epoch @ CustomError::AccountNotUnlocked
+    )]
+    pub lock_account: Account<'info, LockAccount>,
+    #[account(
+        mut,
+        seeds = [b"data", owner.as_ref()],
+        bump,
+    )]
+    pub user_data_account: Account<'info, UserDataAccount>,
+    #[account(
+        seeds = [b"global"],
+        bump,
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+}
+#[derive(Accounts)]
+#[instruction(epoch: u64, source_epoch: u64)]
+pub struct MergeLocks<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"lock", signer.key().as_ref(), epoch.to_le_bytes().as_ref()],
+        bump,
+        constraint = epoch != source_epoch @ CustomError::InvalidLockMerge,
+        constraint = lock_account.unlock_epoch >= source_lock_account.unlock_epoch @ CustomError::InvalidLockMerge
+    )]
+    pub lock_account: Account<'info, LockAccount>,
+    #[account(
+        mut,
+        seeds = [b"lock", signer.key().as_ref(), source_epoch.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub source_lock_account: Account<'info, LockAccount>,
+    #[account(
+        mut,
+        seeds = [b"data", signer.key().as_ref()],
+        bump,
+    )]
+    pub user_data_account: Account<'info, UserDataAccount>,
+    #[account(
+        seeds = [b"global"],
+        bump,
+        constraint = global_data_account.paused_flags & PAUSE_LOCK == 0 @ CustomError::ProgramPaused
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+}
+#[derive(Accounts)]
+#[instruction(epoch: u64, split_epoch: u64)]
+pub struct SplitLock<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"lock", signer.key().as_ref(), epoch.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub lock_account: Account<'info, LockAccount>,
+    #[account(
+        init,
+        seeds = [b"lock", signer.key().as_ref(), split_epoch.to_le_bytes().as_ref()],
+        bump,
+        payer = signer,
+        space = 8 + 8 + 8 + 8 + 32 + 1 + 2 + 8 + 8,
+    )]
+    pub split_lock_account: Account<'info, LockAccount>,
+    #[account(
+        seeds = [b"global"],
+        bump,
+        constraint = global_data_account.paused_flags & PAUSE_LOCK == 0 @ CustomError::ProgramPaused
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+    pub system_program: Program<'info, System>,
+}
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct EarlyUnlock<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = global_data_account.ogg_mint,
+        token::authority = signer,
+    )]
+    pub signer_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"holder", signer.key().as_ref()],
+        bump,
+        token::mint = global_data_account.ogg_mint,
+    )]
+    pub signer_holder_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"lock", signer.key().as_ref(), epoch.to_le_bytes().as_ref()],
+        bump,
     )]
-    pub epoch_account: Account<'info, EpochAccount>,
+    pub lock_account: Account<'info, LockAccount>,
     #[account(
         mut,
-        seeds = [b"epoch", (epoch - 1).to_le_bytes().as_ref()],
+        seeds = [b"data", signer.key().as_ref()],
         bump,
     )]
-    pub prev_epoch_account: Account<'info, EpochAccount>,
+    pub user_data_account: Account<'info, UserDataAccount>,
     #[account(
         mut,
         seeds = [b"global"],
-        bump,    
+        bump,
+        constraint = global_data_account.paused_flags & PAUSE_UNLOCK == 0 @ CustomError::ProgramPaused,
+        has_one = ogg_mint @ CustomError::InvalidMintAccount
     )]
     pub global_data_account: Account<'info, GlobalDataAccount>,
+    #[account(mut)]
+    pub ogg_mint: InterfaceAccount<'info, Mint>,
     #[account(
         mut,
         seeds = [b"holder"],
         bump,
+        token::mint = global_data_account.ogc_mint,
+    )]
+    pub program_holder_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [b"auth"],
+        bump,
     )]
-    pub program_holder_account: Account<'info, TokenAccount>,
+    /// CHECK: 
+    pub program_authority: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[account]
-pub struct LockAccount {
-    pub unlock_epoch: u64,
-    pub epoch: u64,
-    pub owner: Pubkey,
-    pub amount: u64,
+pub struct VoteAccount {
+    owner: Pubkey,
+    epoch: u64,
+    fields: Vec<u64>,
+    commitment: [u8; 32],
+    reserved: u64,
+    revealed: bool,
+    delegate: Pubkey,
+    commission_bps: u16,
+    // amounts cast by the delegate, the part of the fields the commission applies to
+    delegated_fields: Vec<u64>,
+    claimed: bool,
 }
-#[account]
-pub struct UserDataAccount {
-    pub amount: u64,
-    pub staked: u64,
-    pub epoch: u64,
+// adds the amounts to the epoch and vote tallies, checked against the voter's power
+fn tally_vote(epoch_account: &mut EpochAccount, vote_account: &mut VoteAccount, user_data_account: &mut UserDataAccount, epoch: u64, amounts: &[u64], first_vote: bool) -> Result<()> {
+    if first_vote {
+        user_data_account.epoch = epoch;
+        user_data_account.staked = 0;
+        epoch_account.voters += 1;
+    }
+    let mut sum: u64 = 0;
+    for (i, amount) in amounts.iter().enumerate() {
+        epoch_account.fields[i] += amount;
+        vote_account.fields[i] += amount;
+        sum += amount;
+    }
+    vote_account.epoch = epoch;
+    if user_data_account.staked + sum > user_data_account.epoch_power(epoch) {
+        return Err(CustomError::NotEnoughStaked.into())
+    }
+    user_data_account.staked += sum;
+    Ok(())
 }
-#[account]
-pub struct UserStatsAccount {
-    pub owner: Pubkey,
-    pub amount_claimed: u64,
-    pub active_reserve_epochs: u64
+// checks the price against the voter's max fee and pays it to the program authority
+fn charge_vote_fee<'info>(signer: &Signer<'info>, program_authority: &AccountInfo<'info>, system_program: &Program<'info, System>, price: u64, max_fee_lamports: u64) -> Result<()> {
+    if price > max_fee_lamports {
+        return Err(CustomError::FeeExceedsMax.into())
+    }
+    if price == 0 {
+        return Ok(())
+    }
+    anchor_lang::system_program::transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: signer.to_account_info(),
+                to: program_authority.to_account_info(),
+            }
+        ),
+        price,
+    )
 }
 #[derive(Accounts)]
-pub struct CreateStatsAccount<'info> {
+#[instruction(epoch: u64)]
+pub struct CreateVoteAccount<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
     #[account(
         init,
-        seeds = [b"stats", signer.key().as_ref()],
+        seeds = [b"vote", signer.key().as_ref(), epoch.to_le_bytes().as_ref()], 
         bump,
         payer = signer,
-        space = 8 + 8 + 8 + 32
+        space = 8 + 8 + 32 + 4 + 8 * epoch_account.fields.len() + 32 + 8 + 1 + 32 + 2 + 4 + 8 * epoch_account.fields.len() + 1,
     )]
-    pub user_stats_account: Account<'info, UserStatsAccount>,
+    pub vote_account: Account<'info, VoteAccount>,
+    #[account(
+        seeds = [b"epoch", epoch.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub epoch_account: Account<'info, EpochAccount>,
     pub system_program: Program<'info, System>,
 }
 #[derive(Accounts)]
-pub struct CreateDataAccount<'info> {
+#[instruction(epoch: u64)]
+pub struct GetVotePrice<'info> {
+    /// CHECK: only used to derive the voter's data account
+    pub voter: AccountInfo<'info>,
+    #[account(
+        seeds = [b"data", voter.key().as_ref()],
+        bump,
+    )]
+    pub user_data_account: Account<'info, UserDataAccount>,
+    #[account(
+        seeds = [b"epoch", epoch.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub epoch_account: Account<'info, EpochAccount>,
+    #[account(
+        seeds = [b"global"],
+        bump,
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+}
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct Vote<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
     #[account(
-        init,
-        seeds = [b"data", signer.key().as_ref()],
+        mut,
+        seeds = [b"vote", signer.key().as_ref(), epoch.to_le_bytes().as_ref()], 
         bump,
-        payer = signer,
-        space = 8 + 8 + 8 + 8,
     )]
-    pub user_data_account: Account<'info, UserDataAccount>,
+    pub vote_account: Account<'info, VoteAccount>,
     #[account(
-        constraint = mint.key() == global_data_account.ogg_mint @ CustomError::InvalidMintAccount
+        mut,
+        seeds = [b"stats", signer.key().as_ref()],
+        bump,
     )]
-    pub mint: Account<'info, Mint>,
+    pub user_stats_account: Account<'info, UserStatsAccount>,
     #[account(
-        init,
-        seeds = [b"holder", signer.key().as_ref()],
+        mut,
+        seeds = [b"epoch", epoch.to_le_bytes().as_ref()],
         bump,
-        payer = signer,
-        token::mint = mint,
-        token::authority = program_authority,
     )]
-    pub signer_holder_account: Account<'info, TokenAccount>,
+    pub epoch_account: Account<'info, EpochAccount>,
+    #[account(
+        mut,
+        seeds = [b"data", signer.key().as_ref()],
+        bump,
+    )]
+    pub user_data_account: Account<'info, UserDataAccount>,
     #[account(
         seeds = [b"global"],
         bump,
+        constraint = global_data_account.paused_flags & PAUSE_VOTE == 0 @ CustomError::ProgramPaused
     )]
     pub global_data_account: Account<'info, GlobalDataAccount>,
     #[account(
+        mut,
         seeds = [b"auth"],
         bump,
     )]
     /// CHECK: 
     pub program_authority: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+}
+#[account]
+pub struct Delegation {
+    pub delegator: Pubkey,
+    pub delegate: Pubkey,
+    pub commission_bps: u16,
 }
 #[derive(Accounts)]
-#[instruction(epoch: u64)]
-pub struct CreateLockAccount<'info> {
+pub struct Delegate<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
     #[account(
         init,
-        seeds = [b"lock", signer.key().as_ref(), epoch.to_le_bytes().as_ref()],
+        seeds = [b"delegation", signer.key().as_ref()],
         bump,
         payer = signer,
-        space = 8 + 8 + 8 + 8 + 32,
+        space = 8 + 32 + 32 + 2,
     )]
-    pub lock_account: Account<'info, LockAccount>,
+    pub delegation: Account<'info, Delegation>,
     pub system_program: Program<'info, System>,
 }
-
 #[derive(Accounts)]
-#[instruction(epoch: u64)]
-pub struct Lock<'info> {
+pub struct Undelegate<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"delegation", signer.key().as_ref()],
+        bump,
+        close = signer
+    )]
+    pub delegation: Account<'info, Delegation>,
+}
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct DelegateVote<'info> {
     #[account(mut)]
-    pub signer_token_account: Account<'info, TokenAccount>,
+    pub signer: Signer<'info>,
+    /// CHECK: only used to derive the delegator's accounts
+    pub delegator: AccountInfo<'info>,
+    #[account(
+        seeds = [b"delegation", delegator.key().as_ref()],
+        bump,
+        constraint = delegation.delegate == signer.key() @ CustomError::InvalidDelegate
+    )]
+    pub delegation: Account<'info, Delegation>,
+    #[account(
+        init_if_needed,
+        seeds = [b"vote", delegator.key().as_ref(), epoch.to_le_bytes().as_ref()],
+        bump,
+        payer = signer,
+        space = 8 + 8 + 32 + 4 + 8 * epoch_account.fields.len() + 32 + 8 + 1 + 32 + 2 + 4 + 8 * epoch_account.fields.len() + 1,
+    )]
+    pub vote_account: Account<'info, VoteAccount>,
     #[account(
         mut,
-        seeds = [b"holder", signer.key().as_ref()],
+        seeds = [b"stats", delegator.key().as_ref()],
         bump,
     )]
-    pub signer_holder_account: Account<'info, TokenAccount>,
+    pub user_stats_account: Account<'info, UserStatsAccount>,
     #[account(
         mut,
-        seeds = [b"lock", signer.key().as_ref(), epoch.to_le_bytes().as_ref()],
+        seeds = [b"epoch", epoch.to_le_bytes().as_ref()],
         bump,
     )]
-    pub lock_account: Account<'info, LockAccount>,
+    pub epoch_account: Account<'info, EpochAccount>,
     #[account(
         mut,
-        seeds = [b"data", signer.key().as_ref()],
+        seeds = [b"data", delegator.key().as_ref()],
         bump,
     )]
     pub user_data_account: Account<'info, UserDataAccount>,
     #[account(
         seeds = [b"global"],
         bump,
-        constraint = epoch == global_data_account.epoch @ CustomError::IncorrectEpochNum
+        constraint = global_data_account.paused_flags & PAUSE_VOTE == 0 @ CustomError::ProgramPaused
     )]
     pub global_data_account: Account<'info, GlobalDataAccount>,
+    #[account(
+        mut,
+        seeds = [b"auth"],
+        bump,
+    )]
+    /// CHECK: 
+    pub program_authority: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
 }
 #[derive(Accounts)]
 #[instruction(epoch: u64)]
-pub struct Unlock<'info> {
-    pub signer: Signer<'info>,
+pub struct Claim<'info> {
     #[account(mut)]
-    pub signer_token_account: Account<'info, TokenAccount>,
+    pub signer: Signer<'info>,
     #[account(
         mut,
-        seeds = [b"holder", signer.key().as_ref()],
+        token::mint = global_data_account.ogc_mint,
+        token::authority = signer,
+    )]
+    pub signer_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"vote", signer.key().as_ref(), epoch.to_le_bytes().as_ref()],
         bump,
+        constraint = !vote_account.claimed @ CustomError::AlreadyClaimed
     )]
-    pub signer_holder_account: Account<'info, TokenAccount>,
+    pub vote_account: Account<'info, VoteAccount>,
     #[account(
         mut,
-        seeds = [b"lock", signer.key().as_ref(), epoch.to_le_bytes().as_ref()],
+        seeds = [b"stats", signer.key().as_ref()],
         bump,
-        constraint = lock_account.unlock_epoch <= global_data_account.epoch @ CustomError::AccountNotUnlocked
     )]
-    pub lock_account: Account<'info, LockAccount>,
+    pub user_stats_account: Account<'info, UserStatsAccount>,
     #[account(
         mut,
-        seeds = [b"data", signer.key().as_ref()],
+        seeds = [b"epoch", epoch.to_le_bytes().as_ref()],
         bump,
+        constraint = !epoch_account.swept @ CustomError::ClaimWindowExpired
     )]
-    pub user_data_account: Account<'info, UserDataAccount>,
+    pub epoch_account: Account<'info, EpochAccount>,
     #[account(
+        mut,
         seeds = [b"global"],
         bump,
+        constraint = global_data_account.paused_flags & PAUSE_CLAIM == 0 @ CustomError::ProgramPaused,
+        has_one = ogc_mint @ CustomError::InvalidMintAccount
     )]
     pub global_data_account: Account<'info, GlobalDataAccount>,
+    pub ogc_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"holder"],
+        bump,
+        token::mint = global_data_account.ogc_mint,
+    )]
+    pub program_holder_account: InterfaceAccount<'info, TokenAccount>,
+    // only needed when a delegate voted with a commission
+    #[account(
+        mut,
+        token::mint = global_data_account.ogc_mint,
+    )]
+    pub delegate_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         seeds = [b"auth"],
         bump,
@@ -569,80 +2527,71 @@ pub struct Unlock<'info> {
     /// CHECK: 
     pub program_authority: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-}
-
-#[account]
-pub struct VoteAccount {
-    owner: Pubkey,
-    epoch: u64,
-    fields: [u64; 4]
+    pub token_program: Interface<'info, TokenInterface>,
 }
 #[derive(Accounts)]
 #[instruction(epoch: u64)]
-pub struct CreateVoteAccount<'info> {
+pub struct ClaimPool<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
     #[account(
-        init,
-        seeds = [b"vote", signer.key().as_ref(), epoch.to_le_bytes().as_ref()], 
+        mut,
+        token::mint = mint,
+        token::authority = signer,
+    )]
+    pub signer_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [b"vote", signer.key().as_ref(), epoch.to_le_bytes().as_ref()],
         bump,
-        payer = signer,
-        space = 8 + 8 + 32 + 4 * 8,
     )]
     pub vote_account: Account<'info, VoteAccount>,
-    pub system_program: Program<'info, System>,
-}
-#[derive(Accounts)]
-#[instruction(epoch: u64)]
-pub struct Vote<'info> {
-    #[account(mut)]
-    pub signer: Signer<'info>,
     #[account(
-        mut,
-        seeds = [b"vote", signer.key().as_ref(), epoch.to_le_bytes().as_ref()], 
+        seeds = [b"epoch", epoch.to_le_bytes().as_ref()],
         bump,
     )]
-    pub vote_account: Account<'info, VoteAccount>,
+    pub epoch_account: Account<'info, EpochAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
     #[account(
         mut,
-        seeds = [b"stats", signer.key().as_ref()],
+        seeds = [b"pool", epoch.to_le_bytes().as_ref(), mint.key().as_ref()],
         bump,
+        constraint = !reward_pool.swept @ CustomError::ClaimWindowExpired
     )]
-    pub user_stats_account: Account<'info, UserStatsAccount>,
+    pub reward_pool: Account<'info, RewardPool>,
     #[account(
         mut,
-        seeds = [b"epoch", epoch.to_le_bytes().as_ref()],
+        seeds = [b"pool_vault", reward_pool.key().as_ref()],
         bump,
     )]
-    pub epoch_account: Account<'info, EpochAccount>,
+    pub reward_pool_vault: InterfaceAccount<'info, TokenAccount>,
     #[account(
-        mut,
-        seeds = [b"data", signer.key().as_ref()],
+        init,
+        seeds = [b"receipt", reward_pool.key().as_ref(), signer.key().as_ref()],
         bump,
+        payer = signer,
+        space = 8 + 32 + 8 + 8,
     )]
-    pub user_data_account: Account<'info, UserDataAccount>,
+    pub pool_receipt: Account<'info, PoolReceipt>,
     #[account(
         seeds = [b"global"],
         bump,
+        constraint = global_data_account.paused_flags & PAUSE_CLAIM == 0 @ CustomError::ProgramPaused,
     )]
     pub global_data_account: Account<'info, GlobalDataAccount>,
     #[account(
-        mut,
         seeds = [b"auth"],
         bump,
     )]
     /// CHECK: 
     pub program_authority: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 #[derive(Accounts)]
 #[instruction(epoch: u64)]
-pub struct Claim<'info> {
+pub struct CloseVoteAccount<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
-    #[account(mut)]
-    pub signer_token_account: Account<'info, TokenAccount>,
     #[account(
         mut,
         seeds = [b"vote", signer.key().as_ref(), epoch.to_le_bytes().as_ref()],
@@ -651,27 +2600,58 @@ pub struct Claim<'info> {
     )]
     pub vote_account: Account<'info, VoteAccount>,
     #[account(
-        mut,
-        seeds = [b"stats", signer.key().as_ref()],
+        seeds = [b"global"],
         bump,
+        constraint = global_data_account.epoch > epoch + global_data_account.claim_window_epochs @ CustomError::ClaimWindowOpen
     )]
-    pub user_stats_account: Account<'info, UserStatsAccount>,
+    pub global_data_account: Account<'info, GlobalDataAccount>,
+}
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct SweepPool<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [b"global"],
+        bump,
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
     #[account(
         seeds = [b"epoch", epoch.to_le_bytes().as_ref()],
         bump,
     )]
     pub epoch_account: Account<'info, EpochAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
     #[account(
-        seeds = [b"global"],
+        mut,
+        seeds = [b"pool", epoch.to_le_bytes().as_ref(), mint.key().as_ref()],
         bump,
+        constraint = !reward_pool.swept @ CustomError::PoolAlreadySwept
     )]
-    pub global_data_account: Account<'info, GlobalDataAccount>,
+    pub reward_pool: Account<'info, RewardPool>,
     #[account(
         mut,
-        seeds = [b"holder"],
+        seeds = [b"pool_vault", reward_pool.key().as_ref()],
+        bump,
+    )]
+    pub reward_pool_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        seeds = [b"pool", global_data_account.epoch.to_le_bytes().as_ref(), mint.key().as_ref()],
+        bump,
+        payer = signer,
+        space = 8 + 8 + 32 + 32 + 8 + 8 + 1,
+    )]
+    pub next_reward_pool: Account<'info, RewardPool>,
+    #[account(
+        init_if_needed,
+        seeds = [b"pool_vault", next_reward_pool.key().as_ref()],
         bump,
+        payer = signer,
+        token::mint = mint,
+        token::authority = program_authority,
     )]
-    pub program_holder_account: Account<'info, TokenAccount>,
+    pub next_reward_pool_vault: InterfaceAccount<'info, TokenAccount>,
     #[account(
         seeds = [b"auth"],
         bump,
@@ -679,7 +2659,26 @@ pub struct Claim<'info> {
     /// CHECK: 
     pub program_authority: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct SweepEpoch<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"epoch", epoch.to_le_bytes().as_ref()],
+        bump,
+        constraint = !epoch_account.swept @ CustomError::EpochAlreadySwept
+    )]
+    pub epoch_account: Account<'info, EpochAccount>,
+    #[account(
+        mut,
+        seeds = [b"global"],
+        bump,
+        constraint = global_data_account.epoch > epoch + global_data_account.claim_window_epochs @ CustomError::ClaimWindowOpen
+    )]
+    pub global_data_account: Account<'info, GlobalDataAccount>,
 }
 /*
 solana program deploy --skip-fee-check ./program.so --with-compute-unit-price 100 --use-rpc --max-sign-attempts 1000
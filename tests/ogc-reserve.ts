@@ -4,7 +4,8 @@ import { BN } from "bn.js";
 import { PublicKey, Keypair } from "@solana/web3.js";
 import { OgcReserve } from "../target/types/ogc_reserve";
 import { assert } from "chai";
-import { createAssociatedTokenAccount, createMint, getAccount, getAssociatedTokenAddressSync, mintTo } from "@solana/spl-token";
+import { createHash } from "crypto";
+import { TOKEN_PROGRAM_ID, createAssociatedTokenAccount, createMint, getAccount, getAssociatedTokenAddressSync, mintTo } from "@solana/spl-token";
 
 describe("ogc-reserve", () => {
   // Configure the client to use the local cluster.
@@ -14,6 +15,10 @@ describe("ogc-reserve", () => {
   const program = anchor.workspace.OgcReserve as Program<OgcReserve>;
   let ogcMint: PublicKey;
   let oggMint: PublicKey;
+  let multiplierLockEpoch: BN;
+  let poolEpoch: BN;
+  let poolReceiptAddress: PublicKey;
+  const maxFee = new BN(10 ** 9);
   const mintToken = async () => {
     ogcMint = await createMint(
       provider.connection,
@@ -120,26 +125,96 @@ describe("ogc-reserve", () => {
       100000 * 10 ** 6
     )
   }
+  const [globalAccountAddress] = PublicKey.findProgramAddressSync(
+    [Buffer.from("global")],
+    program.programId
+  );
+  const getTime = async () => {
+    const time = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    return time ?? 0;
+  }
+  const waitForEpochEnd = async () => {
+    const globalAccount = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    while (await getTime() < globalAccount.epochEndTime.toNumber()) {
+      await new Promise(resolve => setTimeout(resolve, 1000));
+    }
+  }
+  // advances epochs until the current one stays open for voting long enough
+  const openEpoch = async (minSeconds: number) => {
+    while (true) {
+      const globalAccount = await program.account.globalDataAccount.fetch(globalAccountAddress);
+      if (globalAccount.epochEndTime.toNumber() - await getTime() >= minSeconds) {
+        return;
+      }
+      await waitForEpochEnd();
+      const [prevEpochAccount] = PublicKey.findProgramAddressSync(
+        [Buffer.from("epoch"), globalAccount.epoch.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      await program.methods.newEpoch(globalAccount.epoch.addn(1)).accounts({
+        signer: wallet.publicKey,
+        prevEpochAccount
+      }).rpc();
+    }
+  }
   it("initializes", async () => {
     await mintToken();
     console.log({ ogcMint: ogcMint.toString(), oggMint: oggMint.toString() })
     await program.methods.initializeFirstEpochAccount().accounts({
       signer: wallet.publicKey
     }).rpc();
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+    const outsider = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(outsider.publicKey, 10 ** 9)
+    );
+    try {
+      await program.methods.initialize().accounts({
+        signer: outsider.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        programData,
+        ogcMint,
+        oggMint
+      }).signers([outsider]).rpc();
+      assert(false, "Did not fail to initialize from a non upgrade authority")
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Did not fail to initialize from a non upgrade authority: " + e.message);
+      }
+    }
       await program.methods.initialize().accounts({
         signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        programData,
         ogcMint,
         oggMint
       }).rpc();
     console.log("initialized");
     await program.methods.createDataAccount().accounts({
       signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
       mint: oggMint,
     }).rpc();
     await program.methods.createStatsAccount().accounts({
       signer: wallet.publicKey
     }).rpc();
     console.log("data account created")
+    // voting power only counts from the epoch after it was locked
+    await program.methods.createLockAccount(new BN(0)).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await program.methods.lock(new BN(0), new BN(16000), 0).accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      signerTokenAccount: getAssociatedTokenAddressSync(oggMint, wallet.publicKey),
+    }).rpc();
+    // long enough epochs to vote in before they end
+    await program.methods.modifyGlobalData(new BN(10), new BN(1000), 4, { secondHighestWins: {} }, new BN(2)).accounts({
+      signer: wallet.publicKey
+    }).rpc();
     const [globalAccountAddress] = PublicKey.findProgramAddressSync(
       [Buffer.from("global")],
       program.programId
@@ -151,7 +226,7 @@ describe("ogc-reserve", () => {
     const epochAccount0 = await program.account.epochAccount.fetch(epochAccount0Address);
     let globalAccount = await program.account.globalDataAccount.fetch(globalAccountAddress);
     assert(globalAccount.epoch.eq(new BN(0)), "Incorrect epoch");
-    assert(epochAccount0.winner.eq(new BN(0)), "Incorrect winner");
+    assert(epochAccount0.outcome.noWinner !== undefined, "Incorrect outcome");
     assert(!epochAccount0.fields.find(f => !f.eq(new BN(0))), "Incorrectly set fields");
     const tx2 = await program.methods.newEpoch(new BN(1)).accounts({
       signer: wallet.publicKey,
@@ -163,15 +238,26 @@ describe("ogc-reserve", () => {
     );
     const epochAccount1 = await program.account.epochAccount.fetch(epochAccount1Address);
     globalAccount = await program.account.globalDataAccount.fetch(globalAccountAddress);
-    assert(epochAccount1.winner.eq(new BN(0)), "Incorrect winner");
+    const epochAccount0After = await program.account.epochAccount.fetch(epochAccount0Address);
+    assert(epochAccount0After.outcome.noWinner !== undefined, "Incorrect outcome");
+    assert(epochAccount0After.reward.eq(new BN(0)), "Reward not rolled over");
+    assert(globalAccount.rollover.eq(globalAccount.rewardAmount), "Incorrect rollover");
     assert(globalAccount.epoch.eq(new BN(1)), "Incorrect epoch num");
   });
  it("funds", async () => {
     const signerTokenAccount = getAssociatedTokenAddressSync(ogcMint, wallet.publicKey);
+    let depositedAmount: BN | undefined;
+    const listener = program.addEventListener("rewardsDeposited", (event) => {
+      depositedAmount = event.amount;
+    });
     await program.methods.depositOgg(new BN(100000 * 10 ** 6)).accounts({
       signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
       signerTokenAccount
     }).rpc();
+    await new Promise(resolve => setTimeout(resolve, 1000));
+    await program.removeEventListener(listener);
+    assert(depositedAmount?.eq(new BN(100000 * 10 ** 6)), "Deposit event not emitted");
     const [programHolderAccountAddress] = PublicKey.findProgramAddressSync(
       [Buffer.from("holder")],
       program.programId
@@ -183,6 +269,7 @@ describe("ogc-reserve", () => {
     const signerTokenAccount = getAssociatedTokenAddressSync(ogcMint, wallet.publicKey);
     await program.methods.withdrawOgg(new BN(50000 * 10 ** 6)).accounts({
       signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
       signerTokenAccount
     }).rpc();
     const [programHolderAccountAddress] = PublicKey.findProgramAddressSync(
@@ -198,8 +285,9 @@ describe("ogc-reserve", () => {
       await program.methods.createLockAccount(new BN(1)).accounts({
         signer: wallet.publicKey,
       }).rpc();
-      await program.methods.lock(new BN(1), new BN(100)).accounts({
+      await program.methods.lock(new BN(1), new BN(100), 0).accounts({
         signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
         signerTokenAccount,
       }).rpc();
       const [lockAccountAddress] = PublicKey.findProgramAddressSync(
@@ -209,8 +297,9 @@ describe("ogc-reserve", () => {
       let lockAccount = await program.account.lockAccount.fetch(lockAccountAddress);
       assert(lockAccount.amount.eq(new BN(100)), "Incorrect amount");
       assert(lockAccount.unlockEpoch.eq(new BN(2)), "Incorrect unlock epoch");
-      await program.methods.lock(new BN(1), new BN(100)).accounts({
+      await program.methods.lock(new BN(1), new BN(100), 0).accounts({
         signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
         signerTokenAccount,
       }).rpc();
       lockAccount = await program.account.lockAccount.fetch(lockAccountAddress);
@@ -220,18 +309,21 @@ describe("ogc-reserve", () => {
         [Buffer.from("epoch"), new BN(1).toArrayLike(Buffer, "le", 8)],
         program.programId
       );
+      await waitForEpochEnd();
       await program.methods.newEpoch(new BN(2)).accounts({
         signer: wallet.publicKey,
         prevEpochAccount
       }).rpc();
       await program.methods.unlock(new BN(1), new BN(100)).accounts({
         signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
         signerTokenAccount,
       }).rpc();
       lockAccount = await program.account.lockAccount.fetch(lockAccountAddress);
       assert(lockAccount.amount.eq(new BN(100)), "Incorrect amount");
       await program.methods.unlock(new BN(1), new BN(100)).accounts({
         signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
         signerTokenAccount,
       }).rpc();
       try {
@@ -242,14 +334,6 @@ describe("ogc-reserve", () => {
       }
   })  
   it("votes and claims", async () => {
-    await program.methods.createLockAccount(new BN(2)).accounts({
-      signer: wallet.publicKey
-    }).rpc();
-    const signerTokenAccount = getAssociatedTokenAddressSync(oggMint, wallet.publicKey);
-    await program.methods.lock(new BN(2), new BN(16000)).accounts({
-      signer: wallet.publicKey,
-      signerTokenAccount,
-    }).rpc();
     const [dataAccountAddress] = PublicKey.findProgramAddressSync(
       [Buffer.from("data"), wallet.publicKey.toBuffer()],
       program.programId,
@@ -263,10 +347,15 @@ describe("ogc-reserve", () => {
     await program.methods.createVoteAccount(new BN(2)).accounts({
       signer: wallet.publicKey
     }).rpc();
-    await program.methods.vote(new BN(2), data).accounts({
+    const price = await program.methods.getVotePrice(new BN(2)).accounts({
+      voter: wallet.publicKey
+    }).view();
+    assert(price.eq(new BN(0)), "Incorrect price");
+    await program.methods.vote(new BN(2), data, maxFee).accounts({
       signer: wallet.publicKey
     }).rpc();
-    await program.methods.vote(new BN(2), data).accounts({
+    // topping up votes in the same epoch does not pay the fee again
+    await program.methods.vote(new BN(2), data, new BN(0)).accounts({
       signer: wallet.publicKey
     }).rpc();
     const [voteAccountAddress] = PublicKey.findProgramAddressSync(
@@ -279,6 +368,7 @@ describe("ogc-reserve", () => {
       [Buffer.from("epoch"), new BN(2).toArrayLike(Buffer, "le", 8)],
       program.programId,
     );
+    await waitForEpochEnd();
     await program.methods.newEpoch(new BN(3)).accounts({
       signer: wallet.publicKey,
       prevEpochAccount, 
@@ -297,24 +387,44 @@ describe("ogc-reserve", () => {
     );
     const globalAccount = await program.account.globalDataAccount.fetch(globalAccountAddress);
     const epochAccount2 = await program.account.epochAccount.fetch(prevEpochAccount);
-    assert(epochAccount2.reward.eq(globalAccount.rewardAmount), "Incorrect reward amount");
+    // epochs 0 and 1 had no votes, so their rewards rolled over into epoch 2
+    assert(epochAccount2.reward.eq(globalAccount.rewardAmount.muln(3)), "Incorrect reward amount");
+    assert(epochAccount2.outcome.tie?.[0].length === 4, "Incorrect outcome");
     assert(epochAccount2.voters.eq(new BN(1)), "Invalid amount of voters"); 
+    assert(epochAccount2.winnerRule.secondHighestWins !== undefined, "Incorrect winner rule");
     const signerTokenAccountAddress = getAssociatedTokenAddressSync(ogcMint, wallet.publicKey);
     const signerTokenAccountBefore = await getAccount(provider.connection, signerTokenAccountAddress);
     const userStatsAccountBefore = await program.account.userStatsAccount.fetch(userStatsAccountAddress)
     await program.methods.claim(new BN(2)).accounts({
       signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
       signerTokenAccount: signerTokenAccountAddress,
     }).rpc();
     const userStatsAccountAfter = await program.account.userStatsAccount.fetch(userStatsAccountAddress);
     assert(userStatsAccountBefore.amountClaimed.lte(userStatsAccountAfter.amountClaimed));
+    const epochAccount2After = await program.account.epochAccount.fetch(prevEpochAccount);
+    const globalAccountAfter = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    // the only voter holds every vote on the tied fields, so gets the whole snapshot
+    assert(epochAccount2After.totalClaimed.eq(epochAccount2.reward), "Incorrect claimed amount");
+    assert(globalAccountAfter.reserved.eq(globalAccount.reserved.sub(epochAccount2.reward)), "Incorrect reserved amount");
     await new Promise(resolve => setTimeout(resolve, 1000));
     const signerTokenAccountAfter = await getAccount(provider.connection, signerTokenAccountAddress);
     assert(signerTokenAccountAfter.amount > signerTokenAccountBefore.amount, "Did not get token");
+    voteAccount = await program.account.voteAccount.fetch(voteAccountAddress);
+    assert(voteAccount.claimed, "Vote account not marked claimed");
     try {
-      voteAccount = await program.account.voteAccount.fetch(voteAccountAddress);
-      assert(false, "vote account not deleted");
-    } catch (e) { }
+      await program.methods.claim(new BN(2)).accounts({
+        signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        signerTokenAccount: signerTokenAccountAddress,
+      }).rpc();
+      assert(false, "Claimed twice");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Claimed twice: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "AlreadyClaimed", "Incorrect error");
+    }
   });
   it("fails to vote too much", async () => {
     await program.methods.createVoteAccount(new BN(3)).accounts({
@@ -322,7 +432,7 @@ describe("ogc-reserve", () => {
     }).rpc();
     let data = Array.from({length: 4}).map(() => new BN(10000));
     try {
-      await program.methods.vote(new BN(3), data).accounts({
+      await program.methods.vote(new BN(3), data, maxFee).accounts({
         signer: wallet.publicKey
       }).rpc();
       assert(false, "Did not fail at first staking")
@@ -337,17 +447,17 @@ describe("ogc-reserve", () => {
         [Buffer.from("data"), wallet.publicKey.toBuffer()],
         program.programId
       );
-      await program.methods.vote(new BN(3), data).accounts({
+      await program.methods.vote(new BN(3), data, maxFee).accounts({
         signer: wallet.publicKey
       }).rpc();      
       let dataAccount = await program.account.userDataAccount.fetch(userDataAccountAddress);
       assert(dataAccount.staked.eq(new BN(2000).mul(new BN(4))), "Incorrect amount staked 1");
-      await program.methods.vote(new BN(3), data).accounts({
+      await program.methods.vote(new BN(3), data, maxFee).accounts({
         signer: wallet.publicKey
       }).rpc();
       dataAccount = await program.account.userDataAccount.fetch(userDataAccountAddress);
       assert(dataAccount.staked.eq(new BN(2000).mul(new BN(8))), "Incorrect amount staked 2");
-      await program.methods.vote(new BN(3), data).accounts({
+      await program.methods.vote(new BN(3), data, maxFee).accounts({
         signer: wallet.publicKey
       }).rpc();
       dataAccount = await program.account.userDataAccount.fetch(userDataAccountAddress);
@@ -359,16 +469,1158 @@ describe("ogc-reserve", () => {
       }
     }
   })
+  it("keeps votes backed when unlocking", async () => {
+    const signerTokenAccount = getAssociatedTokenAddressSync(oggMint, wallet.publicKey);
+    try {
+      await program.methods.unlock(new BN(0), new BN(1)).accounts({
+        signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        signerTokenAccount,
+      }).rpc();
+      assert(false, "Unlocked tokens backing votes");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Unlocked tokens backing votes: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "NotEnoughStaked", "Incorrect error");
+    }
+  })
+  it("rejects token accounts with the wrong mint or owner", async () => {
+    const rewardTokenAccount = getAssociatedTokenAddressSync(ogcMint, wallet.publicKey);
+    const stakeTokenAccount = getAssociatedTokenAddressSync(oggMint, wallet.publicKey);
+    const secondRewardTokenAccount = getAssociatedTokenAddressSync(ogcMint, new PublicKey("58V6myLoy5EVJA3U2wPdRDMUXpkwg8Vfw5b6fHqi2mEj"));
+    try {
+      await program.methods.depositOgg(new BN(1)).accounts({
+        signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        signerTokenAccount: stakeTokenAccount,
+      }).rpc();
+      assert(false, "Deposited the staking token as rewards");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Deposited the staking token as rewards: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "ConstraintTokenMint", "Incorrect error");
+    }
+    try {
+      await program.methods.withdrawOgg(new BN(1)).accounts({
+        signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        signerTokenAccount: secondRewardTokenAccount,
+      }).rpc();
+      assert(false, "Withdrew to another owner's account");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Withdrew to another owner's account: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "ConstraintTokenOwner", "Incorrect error");
+    }
+    try {
+      await program.methods.unlock(new BN(0), new BN(1)).accounts({
+        signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        signerTokenAccount: rewardTokenAccount,
+      }).rpc();
+      assert(false, "Unlocked into a reward token account");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Unlocked into a reward token account: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "ConstraintTokenMint", "Incorrect error");
+    }
+  })
+  it("rejects out of range fields", async () => {
+    const data = Array.from({length: 5}).map(() => new BN(1));
+    try {
+      await program.methods.vote(new BN(3), data, maxFee).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Voted on out of range field");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Voted on out of range field: " + e.message);
+      }
+    }
+  })
+  it("sweeps expired epochs", async () => {
+    const [globalAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("global")],
+      program.programId
+    );
+    for (let epoch = 4; epoch <= 5; epoch++) {
+      await waitForEpochEnd();
+      const [prevEpochAccount] = PublicKey.findProgramAddressSync(
+        [Buffer.from("epoch"), new BN(epoch - 1).toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      await program.methods.newEpoch(new BN(epoch)).accounts({
+        signer: wallet.publicKey,
+        prevEpochAccount
+      }).rpc();
+    }
+    try {
+      await program.methods.sweepEpoch(new BN(3)).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Swept epoch inside claim window");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Swept epoch inside claim window: " + e.message);
+      }
+    }
+    const [epochAccount2Address] = PublicKey.findProgramAddressSync(
+      [Buffer.from("epoch"), new BN(2).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const globalAccountBefore = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    await program.methods.sweepEpoch(new BN(2)).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const globalAccountAfter = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    const epochAccount2 = await program.account.epochAccount.fetch(epochAccount2Address);
+    assert(epochAccount2.swept, "Epoch not swept");
+    assert(globalAccountAfter.rollover.sub(globalAccountBefore.rollover).eq(epochAccount2.reward.sub(epochAccount2.totalClaimed)), "Incorrect rollover");
+    // vote accounts stay open through the claim window for pool claims, then return their rent
+    await program.methods.closeVoteAccount(new BN(2)).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const [voteAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vote"), wallet.publicKey.toBuffer(), new BN(2).toArrayLike(Buffer, "le", 8)],
+      program.programId,
+    );
+    assert(await program.account.voteAccount.fetchNullable(voteAccountAddress) === null, "Vote account not closed");
+  })
   it("modifies global data", async () => {
-    await program.methods.modifyGlobalData(new BN(100), new BN(100), new BN(100)).accounts({
+    try {
+      await program.methods.modifyGlobalData(new BN(6), new BN(100), 4, { nth: { 0: 5 } }, new BN(1)).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Accepted a winner rank past the number of fields");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Accepted a winner rank past the number of fields: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "InvalidWinnerRule", "Incorrect error");
+    }
+    await program.methods.modifyGlobalData(new BN(6), new BN(100), 8, { nth: { 0: 3 } }, new BN(1)).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const [globalDataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("global")],
+      program.programId
+    );
+    const globalDataAccount = await program.account.globalDataAccount.fetch(globalDataAccountAddress);
+    assert(globalDataAccount.epochLength.eq(new BN(6)), "Incorrect epoch length")
+    assert(globalDataAccount.rewardAmount.eq(new BN(100)), "Incorrect reward amount")
+    assert(globalDataAccount.numFields === 8, "Incorrect number of fields")
+    assert(globalDataAccount.winnerRule.nth?.[0] === 3, "Incorrect winner rule")
+    assert(globalDataAccount.claimWindowEpochs.eq(new BN(1)), "Incorrect claim window")
+    try {
+      await program.methods.claim(new BN(3)).accounts({
+        signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        signerTokenAccount: getAssociatedTokenAddressSync(ogcMint, wallet.publicKey),
+      }).rpc();
+      assert(false, "Claimed past the claim window");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Claimed past the claim window: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "ClaimWindowExpired", "Incorrect error");
+    }
+  })
+  it("transfers admin", async () => {
+    const [globalDataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("global")],
+      program.programId
+    );
+    const newAdmin = Keypair.generate();
+    await program.methods.proposeAdmin(newAdmin.publicKey).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    let globalDataAccount = await program.account.globalDataAccount.fetch(globalDataAccountAddress);
+    assert(globalDataAccount.admin.equals(wallet.publicKey), "Admin changed before acceptance");
+    assert(globalDataAccount.pendingAdmin.equals(newAdmin.publicKey), "Incorrect pending admin");
+    try {
+      await program.methods.acceptAdmin().accounts({
+        newAdmin: wallet.publicKey
+      }).rpc();
+      assert(false, "Accepted by wrong signer");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Accepted by wrong signer: " + e.message);
+      }
+    }
+    await program.methods.acceptAdmin().accounts({
+      newAdmin: newAdmin.publicKey
+    }).signers([newAdmin]).rpc();
+    globalDataAccount = await program.account.globalDataAccount.fetch(globalDataAccountAddress);
+    assert(globalDataAccount.admin.equals(newAdmin.publicKey), "Admin not transferred");
+    await program.methods.proposeAdmin(wallet.publicKey).accounts({
+      signer: newAdmin.publicKey
+    }).signers([newAdmin]).rpc();
+    await program.methods.acceptAdmin().accounts({
+      newAdmin: wallet.publicKey
+    }).rpc();
+    globalDataAccount = await program.account.globalDataAccount.fetch(globalDataAccountAddress);
+    assert(globalDataAccount.admin.equals(wallet.publicKey), "Admin not transferred back");
+  })
+  it("pauses new epochs", async () => {
+    const [globalDataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("global")],
+      program.programId
+    );
+    try {
+      await program.methods.setPause(1 << 5).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Stored an undefined pause flag");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Stored an undefined pause flag: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "InvalidPauseFlags", "Incorrect error");
+    }
+    const pauseNewEpoch = 1 << 4;
+    await program.methods.setPause(pauseNewEpoch).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    let globalDataAccount = await program.account.globalDataAccount.fetch(globalDataAccountAddress);
+    const epoch = globalDataAccount.epoch.addn(1);
+    const [prevEpochAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("epoch"), globalDataAccount.epoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    try {
+      await program.methods.newEpoch(epoch).accounts({
+        signer: wallet.publicKey,
+        prevEpochAccount
+      }).rpc();
+      assert(false, "Advanced epoch while paused");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Advanced epoch while paused: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "ProgramPaused", "Incorrect error");
+    }
+    await program.methods.setPause(0).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    globalDataAccount = await program.account.globalDataAccount.fetch(globalDataAccountAddress);
+    assert(globalDataAccount.pausedFlags === 0, "Not unpaused");
+  })
+  it("sets vote pricing", async () => {
+    await openEpoch(4);
+    const [globalDataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("global")],
+      program.programId
+    );
+    await program.methods.setVotePricing(new BN(5000), { quadraticCapped: { 0: new BN(1000000) } }, new BN(100)).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const globalDataAccount = await program.account.globalDataAccount.fetch(globalDataAccountAddress);
+    assert(globalDataAccount.feeLamports.eq(new BN(5000)), "Incorrect fee");
+    assert(globalDataAccount.pricingCurve.quadraticCapped?.[0].eq(new BN(1000000)), "Incorrect pricing curve");
+    assert(globalDataAccount.topUpFeeLamports.eq(new BN(100)), "Incorrect top up fee");
+    const epoch = globalDataAccount.epoch;
+    await program.methods.createVoteAccount(epoch).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await program.methods.vote(epoch, [new BN(1)], maxFee).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const price = await program.methods.getVotePrice(epoch).accounts({
+      voter: wallet.publicKey
+    }).view();
+    assert(price.eq(new BN(100)), "Incorrect top up price");
+    try {
+      await program.methods.vote(epoch, [new BN(1)], new BN(0)).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Paid more than max fee");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Paid more than max fee: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "FeeExceedsMax", "Incorrect error");
+    }
+  })
+  it("keeps unlocking open while voting is paused", async () => {
+    // the following tests vote in this epoch too
+    await openEpoch(5);
+    const globalDataAccount = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    const [voteAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vote"), wallet.publicKey.toBuffer(), globalDataAccount.epoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    if (await program.account.voteAccount.fetchNullable(voteAccountAddress) === null) {
+      await program.methods.createVoteAccount(globalDataAccount.epoch).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+    }
+    const pauseVote = 1 << 2;
+    await program.methods.setPause(pauseVote).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    try {
+      await program.methods.vote(globalDataAccount.epoch, [new BN(1)], maxFee).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Voted while paused");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Voted while paused: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "ProgramPaused", "Incorrect error");
+    }
+    const signerTokenAccount = getAssociatedTokenAddressSync(oggMint, wallet.publicKey);
+    const [lockAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lock"), wallet.publicKey.toBuffer(), new BN(0).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const lockAccountBefore = await program.account.lockAccount.fetch(lockAccountAddress);
+    await program.methods.unlock(new BN(0), new BN(1)).accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      signerTokenAccount,
+    }).rpc();
+    const lockAccountAfter = await program.account.lockAccount.fetch(lockAccountAddress);
+    assert(lockAccountAfter.amount.eq(lockAccountBefore.amount.subn(1)), "Unlock blocked by the vote pause");
+    await program.methods.setPause(0).accounts({
       signer: wallet.publicKey
     }).rpc();
+  })
+  it("guards commit reveal voting", async () => {
+    const [globalDataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("global")],
+      program.programId
+    );
+    const globalDataAccount = await program.account.globalDataAccount.fetch(globalDataAccountAddress);
+    try {
+      await program.methods.setCommitReveal(true, globalDataAccount.epochLength).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Reveal phase covers the whole epoch");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Reveal phase covers the whole epoch: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "InvalidRevealLength", "Incorrect error");
+    }
+    try {
+      await program.methods.commitVote(globalDataAccount.epoch, Array(32).fill(1), new BN(1), maxFee).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Committed outside a commit reveal epoch");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Committed outside a commit reveal epoch: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "CommitRevealDisabled", "Incorrect error");
+    }
+  })
+  it("rejects votes after voting closes", async () => {
     const [globalDataAccountAddress] = PublicKey.findProgramAddressSync(
       [Buffer.from("global")],
       program.programId
     );
     const globalDataAccount = await program.account.globalDataAccount.fetch(globalDataAccountAddress);
-    assert(globalDataAccount.epochLength.eq(globalDataAccount.rewardAmount) && globalDataAccount.epochLength.eq(globalDataAccount.epochLockTime), "Incorrect parameter setting")
+    try {
+      await program.methods.setVotingCutoff(globalDataAccount.epochLength).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Voting cutoff covers the whole epoch");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Voting cutoff covers the whole epoch: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "InvalidVotingCutoff", "Incorrect error");
+    }
+    await program.methods.setVotingCutoff(globalDataAccount.epochLength.subn(1)).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    try {
+      await program.methods.vote(globalDataAccount.epoch, [new BN(1)], maxFee).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Voted after voting closed");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Voted after voting closed: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "EpochExpired", "Incorrect error");
+    }
+    try {
+      await program.methods.revote(globalDataAccount.epoch, [new BN(0), new BN(1)], maxFee).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Revoted after voting closed");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Revoted after voting closed: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "EpochExpired", "Incorrect error");
+    }
+    await program.methods.setVotingCutoff(new BN(0)).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+  })
+  it("commits and reveals votes", async () => {
+    const commitment = (epoch: BN, amounts: BN[], salt: number[]) => Array.from(createHash("sha256")
+      .update(wallet.publicKey.toBuffer())
+      .update(epoch.toArrayLike(Buffer, "le", 8))
+      .update(Buffer.concat(amounts.map(amount => amount.toArrayLike(Buffer, "le", 8))))
+      .update(Buffer.from(salt))
+      .digest());
+    const [dataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("data"), wallet.publicKey.toBuffer()],
+      program.programId,
+    );
+    await program.methods.setCommitReveal(true, new BN(2)).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    try {
+      await program.methods.modifyGlobalData(new BN(2), new BN(100), 8, { nth: { 0: 3 } }, new BN(1)).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Shrunk the epoch under the reveal phase");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Shrunk the epoch under the reveal phase: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "InvalidRevealLength", "Incorrect error");
+    }
+    // commit reveal applies from the next epoch
+    await waitForEpochEnd();
+    await openEpoch(4);
+    let globalDataAccount = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    const epoch = globalDataAccount.epoch;
+    const [epochAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("epoch"), epoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [voteAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vote"), wallet.publicKey.toBuffer(), epoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const amounts = [new BN(3), new BN(2)];
+    const salt = Array(32).fill(7);
+    await program.methods.createVoteAccount(epoch).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await program.methods.commitVote(epoch, commitment(epoch, amounts, salt), new BN(10), maxFee).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    let dataAccount = await program.account.userDataAccount.fetch(dataAccountAddress);
+    assert(dataAccount.staked.eq(new BN(10)), "Incorrect reserved amount");
+    const epochAccount = await program.account.epochAccount.fetch(epochAccountAddress);
+    while (await getTime() < epochAccount.revealStartTime.toNumber()) {
+      await new Promise(resolve => setTimeout(resolve, 200));
+    }
+    try {
+      await program.methods.revealVote(epoch, amounts, Array(32).fill(8)).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Revealed with the wrong salt");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Revealed with the wrong salt: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "InvalidReveal", "Incorrect error");
+    }
+    await program.methods.revealVote(epoch, amounts, salt).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const epochAccountAfter = await program.account.epochAccount.fetch(epochAccountAddress);
+    const voteAccount = await program.account.voteAccount.fetch(voteAccountAddress);
+    assert(epochAccountAfter.fields[0].eq(new BN(3)) && epochAccountAfter.fields[1].eq(new BN(2)), "Reveal not tallied");
+    assert(voteAccount.fields[0].eq(new BN(3)) && voteAccount.fields[1].eq(new BN(2)), "Incorrect revealed votes");
+    dataAccount = await program.account.userDataAccount.fetch(dataAccountAddress);
+    // the unused part of the reservation is released
+    assert(dataAccount.staked.eq(new BN(5)), "Reservation not released");
+    // a commitment that is never revealed claims nothing
+    await openEpoch(4);
+    globalDataAccount = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    const unrevealedEpoch = globalDataAccount.epoch;
+    await program.methods.createVoteAccount(unrevealedEpoch).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await program.methods.commitVote(unrevealedEpoch, commitment(unrevealedEpoch, amounts, salt), new BN(10), maxFee).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await program.methods.setCommitReveal(false, new BN(0)).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await waitForEpochEnd();
+    const [prevEpochAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("epoch"), unrevealedEpoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods.newEpoch(unrevealedEpoch.addn(1)).accounts({
+      signer: wallet.publicKey,
+      prevEpochAccount
+    }).rpc();
+    const signerTokenAccount = getAssociatedTokenAddressSync(ogcMint, wallet.publicKey);
+    const signerTokenAccountBefore = await getAccount(provider.connection, signerTokenAccount);
+    let claimedAmount: BN | undefined;
+    const listener = program.addEventListener("claimed", (event) => {
+      claimedAmount = event.amount;
+    });
+    await program.methods.claim(unrevealedEpoch).accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      signerTokenAccount,
+    }).rpc();
+    await new Promise(resolve => setTimeout(resolve, 1000));
+    await program.removeEventListener(listener);
+    const signerTokenAccountAfter = await getAccount(provider.connection, signerTokenAccount);
+    assert(claimedAmount?.eq(new BN(0)), "Unrevealed vote claimed a reward");
+    assert(signerTokenAccountAfter.amount === signerTokenAccountBefore.amount, "Unrevealed vote received tokens");
+  })
+  it("revotes and unvotes", async () => {
+    const [dataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("data"), wallet.publicKey.toBuffer()],
+      program.programId,
+    );
+    const [programAuthorityAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("auth")],
+      program.programId
+    );
+    await openEpoch(4);
+    const globalDataAccount = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    const epoch = globalDataAccount.epoch;
+    const [epochAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("epoch"), epoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [voteAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vote"), wallet.publicKey.toBuffer(), epoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods.createVoteAccount(epoch).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await program.methods.vote(epoch, [new BN(2), new BN(2)], maxFee).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    try {
+      await program.methods.revote(epoch, [new BN(1), new BN(5)], new BN(0)).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Raised votes without paying the top up fee");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Raised votes without paying the top up fee: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "FeeExceedsMax", "Incorrect error");
+    }
+    const balanceBefore = await provider.connection.getBalance(programAuthorityAddress);
+    await program.methods.revote(epoch, [new BN(1), new BN(5)], maxFee).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const balanceAfter = await provider.connection.getBalance(programAuthorityAddress);
+    assert(balanceAfter - balanceBefore === globalDataAccount.topUpFeeLamports.toNumber(), "Top up fee not charged");
+    let epochAccount = await program.account.epochAccount.fetch(epochAccountAddress);
+    let voteAccount = await program.account.voteAccount.fetch(voteAccountAddress);
+    let dataAccount = await program.account.userDataAccount.fetch(dataAccountAddress);
+    assert(epochAccount.fields[0].eq(new BN(1)) && epochAccount.fields[1].eq(new BN(5)), "Incorrect epoch fields after revote");
+    assert(voteAccount.fields[0].eq(new BN(1)) && voteAccount.fields[1].eq(new BN(5)), "Incorrect vote fields after revote");
+    assert(dataAccount.staked.eq(new BN(6)), "Incorrect staked amount after revote");
+    await program.methods.unvote(epoch).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    epochAccount = await program.account.epochAccount.fetch(epochAccountAddress);
+    voteAccount = await program.account.voteAccount.fetch(voteAccountAddress);
+    dataAccount = await program.account.userDataAccount.fetch(dataAccountAddress);
+    assert(!epochAccount.fields.find(f => !f.eq(new BN(0))), "Epoch fields not cleared by unvote");
+    assert(!voteAccount.fields.find(f => !f.eq(new BN(0))), "Vote fields not cleared by unvote");
+    assert(dataAccount.staked.eq(new BN(0)), "Staked amount not released by unvote");
+  })
+  it("locks with a voting power multiplier", async () => {
+    await program.methods.setLockTiers([
+      { lockEpochs: new BN(1), multiplierBps: 10000 },
+      { lockEpochs: new BN(4), multiplierBps: 20000 },
+    ]).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const [globalDataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("global")],
+      program.programId
+    );
+    const [dataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("data"), wallet.publicKey.toBuffer()],
+      program.programId,
+    );
+    const globalDataAccount = await program.account.globalDataAccount.fetch(globalDataAccountAddress);
+    const epoch = globalDataAccount.epoch;
+    const signerTokenAccount = getAssociatedTokenAddressSync(oggMint, wallet.publicKey);
+    await program.methods.createLockAccount(epoch).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const dataAccountBefore = await program.account.userDataAccount.fetch(dataAccountAddress);
+    await program.methods.lock(epoch, new BN(1000), 1).accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      signerTokenAccount,
+    }).rpc();
+    const dataAccountAfter = await program.account.userDataAccount.fetch(dataAccountAddress);
+    const [lockAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lock"), wallet.publicKey.toBuffer(), epoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const lockAccount = await program.account.lockAccount.fetch(lockAccountAddress);
+    assert(lockAccount.tier === 1, "Incorrect tier");
+    assert(lockAccount.unlockEpoch.eq(epoch.addn(4)), "Incorrect unlock epoch");
+    assert(dataAccountAfter.amount.sub(dataAccountBefore.amount).eq(new BN(1000)), "Incorrect amount");
+    assert(dataAccountAfter.votingPower.sub(dataAccountBefore.votingPower).eq(new BN(2000)), "Incorrect voting power");
+    assert(dataAccountAfter.snapshotEpoch.eq(epoch), "Incorrect snapshot epoch");
+    assert(dataAccountAfter.snapshotPower.eq(dataAccountBefore.votingPower), "Snapshot includes the new lock");
+    multiplierLockEpoch = epoch;
+    try {
+      await program.methods.settleLock(wallet.publicKey, epoch).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Settled a lock before it matured");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Settled a lock before it matured: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "AccountNotUnlocked", "Incorrect error");
+    }
+  })
+  it("unlocks early with a penalty", async () => {
+    const [globalDataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("global")],
+      program.programId
+    );
+    const globalDataAccount = await program.account.globalDataAccount.fetch(globalDataAccountAddress);
+    const epoch = globalDataAccount.epoch;
+    const signerTokenAccount = getAssociatedTokenAddressSync(oggMint, wallet.publicKey);
+    try {
+      await program.methods.earlyUnlock(epoch, new BN(500)).accounts({
+        signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        signerTokenAccount,
+        oggMint,
+      }).rpc();
+      assert(false, "Unlocked early while disabled");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Unlocked early while disabled: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "EarlyUnlockDisabled", "Incorrect error");
+    }
+    try {
+      await program.methods.setEarlyUnlock(true, 1000, false, false).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Routed staking token penalties to the reward pool");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Routed staking token penalties to the reward pool: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "InvalidMintAccount", "Incorrect error");
+    }
+    await program.methods.setEarlyUnlock(true, 1000, false, true).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const signerTokenAccountBefore = await getAccount(provider.connection, signerTokenAccount);
+    await program.methods.earlyUnlock(epoch, new BN(500)).accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      signerTokenAccount,
+      oggMint,
+    }).rpc();
+    const signerTokenAccountAfter = await getAccount(provider.connection, signerTokenAccount);
+    const received = new BN(signerTokenAccountAfter.amount.toString()).sub(new BN(signerTokenAccountBefore.amount.toString()));
+    assert(received.eq(new BN(450)), "Incorrect amount after penalty");
+  })
+  it("splits, merges and extends locks", async () => {
+    const [globalDataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("global")],
+      program.programId
+    );
+    const [dataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("data"), wallet.publicKey.toBuffer()],
+      program.programId,
+    );
+    const globalDataAccount = await program.account.globalDataAccount.fetch(globalDataAccountAddress);
+    const epoch = globalDataAccount.epoch;
+    const splitEpoch = new BN(1000000);
+    const [lockAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lock"), wallet.publicKey.toBuffer(), epoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [splitLockAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lock"), wallet.publicKey.toBuffer(), splitEpoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const dataAccountBefore = await program.account.userDataAccount.fetch(dataAccountAddress);
+    const lockAccountBefore = await program.account.lockAccount.fetch(lockAccountAddress);
+    await program.methods.splitLock(epoch, splitEpoch, new BN(200)).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    let lockAccount = await program.account.lockAccount.fetch(lockAccountAddress);
+    const splitLockAccount = await program.account.lockAccount.fetch(splitLockAccountAddress);
+    assert(lockAccount.amount.eq(lockAccountBefore.amount.subn(200)), "Incorrect amount after split");
+    assert(splitLockAccount.amount.eq(new BN(200)), "Incorrect split amount");
+    assert(splitLockAccount.unlockEpoch.eq(lockAccount.unlockEpoch), "Incorrect split unlock epoch");
+    await program.methods.mergeLocks(epoch, splitEpoch).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    lockAccount = await program.account.lockAccount.fetch(lockAccountAddress);
+    assert(lockAccount.amount.eq(lockAccountBefore.amount), "Incorrect amount after merge");
+    try {
+      await program.account.lockAccount.fetch(splitLockAccountAddress);
+      assert(false, "Merged lock account should have been closed");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error(e.message);
+      }
+    }
+    const dataAccountAfter = await program.account.userDataAccount.fetch(dataAccountAddress);
+    assert(dataAccountAfter.amount.eq(dataAccountBefore.amount), "Locked amount changed");
+    assert(dataAccountAfter.votingPower.eq(dataAccountBefore.votingPower), "Voting power changed");
+    try {
+      await program.methods.extendLock(epoch, 0).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Shortened a lock");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Shortened a lock: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "InvalidLockExtension", "Incorrect error");
+    }
+  })
+  it("votes through a delegate", async () => {
+    const delegate = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(delegate.publicKey, 10 ** 9)
+    );
+    const delegateTokenAccount = await createAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer,
+      ogcMint,
+      delegate.publicKey,
+    );
+    await program.methods.delegate(delegate.publicKey, 500).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await waitForEpochEnd();
+    await openEpoch(4);
+    const [globalDataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("global")],
+      program.programId
+    );
+    const globalDataAccount = await program.account.globalDataAccount.fetch(globalDataAccountAddress);
+    const epoch = globalDataAccount.epoch;
+    const [voteAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vote"), wallet.publicKey.toBuffer(), epoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    try {
+      await program.methods.delegateVote(epoch, [new BN(1)], maxFee).accounts({
+        signer: wallet.publicKey,
+        delegator: wallet.publicKey,
+      }).rpc();
+      assert(false, "Voted without being the delegate");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Voted without being the delegate: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "InvalidDelegate", "Incorrect error");
+    }
+    // the delegate creates the delegator's vote account if they have not voted yet
+    await program.methods.delegateVote(epoch, [new BN(1)], maxFee).accounts({
+      signer: delegate.publicKey,
+      delegator: wallet.publicKey,
+    }).signers([delegate]).rpc();
+    // votes the delegator casts themselves are not subject to the commission
+    await program.methods.vote(epoch, [new BN(0), new BN(1), new BN(1)], maxFee).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const voteAccount = await program.account.voteAccount.fetch(voteAccountAddress);
+    assert(voteAccount.owner.equals(wallet.publicKey), "Incorrect vote owner");
+    assert(voteAccount.delegate.equals(delegate.publicKey), "Incorrect delegate");
+    assert(voteAccount.commissionBps === 500, "Incorrect commission");
+    assert(voteAccount.fields[0].eq(new BN(1)) && voteAccount.fields[1].eq(new BN(1)), "Incorrect vote amount");
+    assert(voteAccount.delegatedFields[0].eq(new BN(1)) && voteAccount.delegatedFields[1].eq(new BN(0)), "Incorrect delegated amount");
+    await program.methods.undelegate().accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await waitForEpochEnd();
+    const [prevEpochAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("epoch"), epoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods.newEpoch(epoch.addn(1)).accounts({
+      signer: wallet.publicKey,
+      prevEpochAccount
+    }).rpc();
+    const signerTokenAccount = getAssociatedTokenAddressSync(ogcMint, wallet.publicKey);
+    try {
+      await program.methods.claim(epoch).accounts({
+        signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        signerTokenAccount,
+      }).rpc();
+      assert(false, "Claimed without paying the delegate");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Claimed without paying the delegate: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "InvalidDelegateAccount", "Incorrect error");
+    }
+    const epochAccount = await program.account.epochAccount.fetch(prevEpochAccount);
+    const signerTokenAccountBefore = await getAccount(provider.connection, signerTokenAccount);
+    await program.methods.claim(epoch).accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      signerTokenAccount,
+      delegateTokenAccount,
+    }).rpc();
+    // three fields tie with one vote each, so the delegate's field earns a third of the reward
+    const split = epochAccount.reward.divn(3);
+    const commission = split.muln(500).divn(10000);
+    const delegateTokenAccountAfter = await getAccount(provider.connection, delegateTokenAccount);
+    const signerTokenAccountAfter = await getAccount(provider.connection, signerTokenAccount);
+    const received = new BN(signerTokenAccountAfter.amount.toString()).sub(new BN(signerTokenAccountBefore.amount.toString()));
+    assert(new BN(delegateTokenAccountAfter.amount.toString()).eq(commission), "Incorrect commission paid");
+    assert(received.eq(split.muln(3).sub(commission)), "Incorrect reward after commission");
+  })
+  it("claims sponsored reward pools", async () => {
+    const sponsorMint = await createMint(
+      provider.connection,
+      wallet.payer,
+      wallet.publicKey,
+      wallet.publicKey,
+      6,
+    );
+    const sponsorTokenAccount = await createAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer,
+      sponsorMint,
+      wallet.publicKey,
+    );
+    await mintTo(
+      provider.connection,
+      wallet.payer,
+      sponsorMint,
+      sponsorTokenAccount,
+      wallet.payer,
+      1200
+    )
+    // start from an epoch the delegate has not voted in
+    await waitForEpochEnd();
+    await openEpoch(4);
+    const globalDataAccount = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    const epoch = globalDataAccount.epoch;
+    await program.methods.depositReward(epoch, new BN(1200)).accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      signerTokenAccount: sponsorTokenAccount,
+      mint: sponsorMint,
+    }).rpc();
+    const [rewardPoolAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool"), epoch.toArrayLike(Buffer, "le", 8), sponsorMint.toBuffer()],
+      program.programId
+    );
+    const [rewardPoolVaultAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_vault"), rewardPoolAddress.toBuffer()],
+      program.programId
+    );
+    const rewardPool = await program.account.rewardPool.fetch(rewardPoolAddress);
+    assert(rewardPool.amount.eq(new BN(1200)), "Incorrect pool amount");
+    await program.methods.createVoteAccount(epoch).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await program.methods.vote(epoch, [new BN(1), new BN(1), new BN(1)], maxFee).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await waitForEpochEnd();
+    const [prevEpochAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("epoch"), epoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods.newEpoch(epoch.addn(1)).accounts({
+      signer: wallet.publicKey,
+      prevEpochAccount
+    }).rpc();
+    await program.methods.claim(epoch).accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      signerTokenAccount: getAssociatedTokenAddressSync(ogcMint, wallet.publicKey),
+    }).rpc();
+    // pools are claimed separately from the base reward, and still can be after it
+    await program.methods.claimPool(epoch).accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      signerTokenAccount: sponsorTokenAccount,
+      mint: sponsorMint,
+    }).rpc();
+    // the only voter holds every vote on the tied fields, so gets the whole pool
+    const sponsorTokenAccountAfter = await getAccount(provider.connection, sponsorTokenAccount);
+    assert(new BN(sponsorTokenAccountAfter.amount.toString()).eq(new BN(1200)), "Incorrect sponsored reward");
+    const rewardPoolAfter = await program.account.rewardPool.fetch(rewardPoolAddress);
+    assert(rewardPoolAfter.totalClaimed.eq(new BN(1200)), "Incorrect pool claimed amount");
+    poolEpoch = epoch;
+    [poolReceiptAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt"), rewardPoolAddress.toBuffer(), wallet.publicKey.toBuffer()],
+      program.programId
+    );
+    const poolReceipt = await program.account.poolReceipt.fetch(poolReceiptAddress);
+    assert(poolReceipt.amount.eq(new BN(1200)), "Incorrect receipt amount");
+    const rewardPoolVault = await getAccount(provider.connection, rewardPoolVaultAddress);
+    assert(new BN(rewardPoolVault.amount.toString()).eq(new BN(0)), "Pool vault not emptied");
+    try {
+      await program.methods.claimPool(epoch).accounts({
+        signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        signerTokenAccount: sponsorTokenAccount,
+        mint: sponsorMint,
+      }).rpc();
+      assert(false, "Claimed a pool twice");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Claimed a pool twice: " + e.message);
+      }
+    }
+  })
+  it("rolls over pools of epochs without a winner", async () => {
+    const sponsorMint = await createMint(
+      provider.connection,
+      wallet.payer,
+      wallet.publicKey,
+      wallet.publicKey,
+      6,
+    );
+    const sponsorTokenAccount = await createAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer,
+      sponsorMint,
+      wallet.publicKey,
+    );
+    await mintTo(
+      provider.connection,
+      wallet.payer,
+      sponsorMint,
+      sponsorTokenAccount,
+      wallet.payer,
+      300
+    )
+    const globalDataAccount = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    const epoch = globalDataAccount.epoch;
+    await program.methods.depositReward(epoch, new BN(300)).accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      signerTokenAccount: sponsorTokenAccount,
+      mint: sponsorMint,
+    }).rpc();
+    try {
+      await program.methods.sweepPool(epoch).accounts({
+        signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        mint: sponsorMint,
+      }).rpc();
+      assert(false, "Swept a pool of an open epoch");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Swept a pool of an open epoch: " + e.message);
+      }
+    }
+    // nobody votes, so the epoch ends without a winner
+    await waitForEpochEnd();
+    const [prevEpochAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("epoch"), epoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods.newEpoch(epoch.addn(1)).accounts({
+      signer: wallet.publicKey,
+      prevEpochAccount
+    }).rpc();
+    const [rewardPoolAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool"), epoch.toArrayLike(Buffer, "le", 8), sponsorMint.toBuffer()],
+      program.programId
+    );
+    const [nextRewardPoolAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool"), epoch.addn(1).toArrayLike(Buffer, "le", 8), sponsorMint.toBuffer()],
+      program.programId
+    );
+    const [nextRewardPoolVaultAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_vault"), nextRewardPoolAddress.toBuffer()],
+      program.programId
+    );
+    await program.methods.sweepPool(epoch).accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      mint: sponsorMint,
+      nextRewardPool: nextRewardPoolAddress,
+      nextRewardPoolVault: nextRewardPoolVaultAddress,
+    }).rpc();
+    const rewardPool = await program.account.rewardPool.fetch(rewardPoolAddress);
+    const nextRewardPool = await program.account.rewardPool.fetch(nextRewardPoolAddress);
+    const nextRewardPoolVault = await getAccount(provider.connection, nextRewardPoolVaultAddress);
+    assert(rewardPool.swept, "Pool not swept");
+    assert(nextRewardPool.amount.eq(new BN(300)), "Pool not rolled over");
+    assert(new BN(nextRewardPoolVault.amount.toString()).eq(new BN(300)), "Pool tokens not moved");
+    try {
+      await program.methods.sweepPool(epoch).accounts({
+        signer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        mint: sponsorMint,
+        nextRewardPool: nextRewardPoolAddress,
+        nextRewardPoolVault: nextRewardPoolVaultAddress,
+      }).rpc();
+      assert(false, "Swept a pool twice");
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Swept a pool twice: " + e.message);
+      }
+      assert(e.error?.errorCode?.code === "PoolAlreadySwept", "Incorrect error");
+    }
+    // the claimed pool's receipt returns its rent with the vote account once the claim window is over
+    await program.methods.closeVoteAccount(poolEpoch).accounts({
+      signer: wallet.publicKey
+    }).remainingAccounts([
+      { pubkey: poolReceiptAddress, isWritable: true, isSigner: false },
+    ]).rpc();
+    assert(await program.account.poolReceipt.fetchNullable(poolReceiptAddress) === null, "Pool receipt not closed");
+  })
+  it("follows the emission schedule", async () => {
+    try {
+      await program.methods.setEmissionSchedule(true, new BN(400), new BN(1), new BN(0), new BN(0)).accounts({
+        signer: wallet.publicKey
+      }).rpc();
+      assert(false, "Did not fail with zero halving epochs")
+    } catch (e) {
+      if (e.name === "AssertionError") {
+        throw new Error("Did not fail with zero halving epochs: " + e.message);
+      }
+    }
+    const globalDataAccount = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    await program.methods.setEmissionSchedule(true, new BN(400), new BN(1), new BN(1), globalDataAccount.totalEmitted.addn(500)).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const scheduled = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    assert(scheduled.emissionSchedule.startEpoch.eq(globalDataAccount.epoch), "Incorrect schedule start epoch");
+    await waitForEpochEnd();
+    const [prevEpochAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("epoch"), globalDataAccount.epoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods.newEpoch(globalDataAccount.epoch.addn(1)).accounts({
+      signer: wallet.publicKey,
+      prevEpochAccount
+    }).rpc();
+    // nobody voted, so the emission waits in the rollover and is not counted until it is funded
+    const globalDataAccountRolled = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    assert(globalDataAccountRolled.totalEmitted.eq(globalDataAccount.totalEmitted), "Unfunded emission counted");
+    assert(globalDataAccountRolled.rolloverEmission.eq(new BN(400)), "Emission not carried in the rollover");
+    await program.methods.createVoteAccount(globalDataAccountRolled.epoch).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await program.methods.vote(globalDataAccountRolled.epoch, [new BN(1), new BN(1), new BN(1)], maxFee).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await waitForEpochEnd();
+    const [rolledEpochAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("epoch"), globalDataAccountRolled.epoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods.newEpoch(globalDataAccountRolled.epoch.addn(1)).accounts({
+      signer: wallet.publicKey,
+      prevEpochAccount: rolledEpochAccount
+    }).rpc();
+    // the winning epoch funds the rolled over 400 and its own halved 200, capped at the 100 left
+    const globalDataAccountAfter = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    assert(globalDataAccountAfter.totalEmitted.sub(globalDataAccount.totalEmitted).eq(new BN(500)), "Incorrect emission");
+    assert(globalDataAccountAfter.rolloverEmission.eq(new BN(0)), "Funded emission left in the rollover");
+    // updating or disabling the schedule keeps its start, so the curve cannot be restarted
+    await program.methods.setEmissionSchedule(true, new BN(400), new BN(1), new BN(1), scheduled.emissionSchedule.totalCap).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const updated = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    assert(updated.emissionSchedule.startEpoch.eq(globalDataAccount.epoch), "Schedule start epoch reset");
+    await program.methods.setEmissionSchedule(false, new BN(0), new BN(0), new BN(0), new BN(0)).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const disabled = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    assert(!disabled.emissionSchedule.enabled, "Schedule not disabled");
+    assert(disabled.emissionSchedule.startEpoch.eq(globalDataAccount.epoch), "Schedule start epoch reset");
+    // flat reward_amount epochs do not count towards the schedule's cap
+    await waitForEpochEnd();
+    const [lastEpochAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("epoch"), globalDataAccountAfter.epoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods.newEpoch(globalDataAccountAfter.epoch.addn(1)).accounts({
+      signer: wallet.publicKey,
+      prevEpochAccount: lastEpochAccount
+    }).rpc();
+    const globalDataAccountFlat = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    assert(globalDataAccountFlat.totalEmitted.eq(globalDataAccountAfter.totalEmitted), "Flat reward counted as emitted");
+  })
+  it("settles matured locks back to 1x", async () => {
+    const [dataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("data"), wallet.publicKey.toBuffer()],
+      program.programId,
+    );
+    const [lockAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lock"), wallet.publicKey.toBuffer(), multiplierLockEpoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const globalDataAccount = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    const lockAccountBefore = await program.account.lockAccount.fetch(lockAccountAddress);
+    assert(lockAccountBefore.unlockEpoch.lte(globalDataAccount.epoch), "Lock not matured yet");
+    assert(lockAccountBefore.power.gt(lockAccountBefore.amount), "Lock has no multiplier to settle");
+    const dataAccountBefore = await program.account.userDataAccount.fetch(dataAccountAddress);
+    // anyone can settle someone else's matured lock
+    const settler = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(settler.publicKey, 10 ** 9)
+    );
+    await program.methods.settleLock(wallet.publicKey, multiplierLockEpoch).accounts({
+      signer: settler.publicKey
+    }).signers([settler]).rpc();
+    const lockAccountAfter = await program.account.lockAccount.fetch(lockAccountAddress);
+    const dataAccountAfter = await program.account.userDataAccount.fetch(dataAccountAddress);
+    assert(lockAccountAfter.power.eq(lockAccountAfter.amount), "Lock power not back to 1x");
+    assert(lockAccountAfter.multiplierBps === 10000, "Incorrect multiplier");
+    assert(dataAccountBefore.votingPower.sub(dataAccountAfter.votingPower).eq(lockAccountBefore.power.sub(lockAccountBefore.amount)), "Incorrect voting power");
+  })
+  it("keeps merged locks for the full tier duration", async () => {
+    // re-committing the matured lock restores its multiplier
+    let globalDataAccount = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    await program.methods.extendLock(multiplierLockEpoch, 1).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const [lockAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lock"), wallet.publicKey.toBuffer(), multiplierLockEpoch.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    let lockAccount = await program.account.lockAccount.fetch(lockAccountAddress);
+    assert(lockAccount.unlockEpoch.eq(globalDataAccount.epoch.addn(4)), "Incorrect extended unlock epoch");
+    assert(lockAccount.power.eq(lockAccount.amount.muln(2)), "Multiplier not restored");
+    await waitForEpochEnd();
+    await openEpoch(4);
+    globalDataAccount = await program.account.globalDataAccount.fetch(globalAccountAddress);
+    const epoch = globalDataAccount.epoch;
+    const signerTokenAccount = getAssociatedTokenAddressSync(oggMint, wallet.publicKey);
+    await program.methods.createLockAccount(epoch).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await program.methods.lock(epoch, new BN(100), 0).accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      signerTokenAccount,
+    }).rpc();
+    // the short lock matures before the tier 1 lock, but its tokens now earn the tier 1 multiplier
+    await program.methods.mergeLocks(multiplierLockEpoch, epoch).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    lockAccount = await program.account.lockAccount.fetch(lockAccountAddress);
+    assert(lockAccount.unlockEpoch.eq(epoch.addn(4)), "Merged lock not pushed out to the tier duration");
   })
   it("withdraws sol", async () => {
     const [programAuthorityAddress] = PublicKey.findProgramAddressSync(
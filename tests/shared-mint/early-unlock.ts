@@ -0,0 +1,99 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { BN } from "bn.js";
+import { PublicKey } from "@solana/web3.js";
+import { OgcReserve } from "../../target/types/ogc_reserve";
+import { assert } from "chai";
+import { TOKEN_PROGRAM_ID, createAssociatedTokenAccount, createMint, getAccount, mintTo } from "@solana/spl-token";
+
+describe("ogc-reserve with a shared staking and reward mint", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const wallet = provider.wallet as anchor.Wallet;
+  const program = anchor.workspace.OgcReserve as Program<OgcReserve>;
+  let mint: PublicKey;
+  let signerTokenAccount: PublicKey;
+  it("initializes", async () => {
+    mint = await createMint(
+      provider.connection,
+      wallet.payer,
+      wallet.publicKey,
+      wallet.publicKey,
+      6,
+    );
+    signerTokenAccount = await createAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer,
+      mint,
+      wallet.publicKey,
+    );
+    await mintTo(
+      provider.connection,
+      wallet.payer,
+      mint,
+      signerTokenAccount,
+      wallet.payer,
+      100000 * 10 ** 6
+    )
+    await program.methods.initializeFirstEpochAccount().accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+    await program.methods.initialize().accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      programData,
+      ogcMint: mint,
+      oggMint: mint
+    }).rpc();
+    await program.methods.createDataAccount().accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      mint,
+    }).rpc();
+    await program.methods.createStatsAccount().accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await program.methods.createLockAccount(new BN(0)).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await program.methods.lock(new BN(0), new BN(1000), 0).accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      signerTokenAccount,
+    }).rpc();
+  })
+  it("adds early unlock penalties to the reward pool", async () => {
+    const [programHolderAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("holder")],
+      program.programId
+    );
+    await program.methods.setEarlyUnlock(true, 1000, false, false).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const [globalDataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("global")],
+      program.programId
+    );
+    const globalDataAccountBefore = await program.account.globalDataAccount.fetch(globalDataAccountAddress);
+    const signerTokenAccountBefore = await getAccount(provider.connection, signerTokenAccount);
+    const programHolderAccountBefore = await getAccount(provider.connection, programHolderAccountAddress);
+    await program.methods.earlyUnlock(new BN(0), new BN(500)).accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      signerTokenAccount,
+      oggMint: mint,
+    }).rpc();
+    const signerTokenAccountAfter = await getAccount(provider.connection, signerTokenAccount);
+    const programHolderAccountAfter = await getAccount(provider.connection, programHolderAccountAddress);
+    const received = new BN(signerTokenAccountAfter.amount.toString()).sub(new BN(signerTokenAccountBefore.amount.toString()));
+    const added = new BN(programHolderAccountAfter.amount.toString()).sub(new BN(programHolderAccountBefore.amount.toString()));
+    assert(received.eq(new BN(450)), "Incorrect amount after penalty");
+    assert(added.eq(new BN(50)), "Penalty not added to the reward pool");
+    const globalDataAccountAfter = await program.account.globalDataAccount.fetch(globalDataAccountAddress);
+    assert(globalDataAccountAfter.rollover.sub(globalDataAccountBefore.rollover).eq(new BN(50)), "Penalty not rolled into the next reward");
+  })
+});
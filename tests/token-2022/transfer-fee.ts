@@ -0,0 +1,133 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { BN } from "bn.js";
+import { Keypair, PublicKey, SystemProgram, Transaction, sendAndConfirmTransaction } from "@solana/web3.js";
+import { OgcReserve } from "../../target/types/ogc_reserve";
+import { assert } from "chai";
+import {
+  ExtensionType,
+  TOKEN_2022_PROGRAM_ID,
+  createAssociatedTokenAccount,
+  createInitializeMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
+  createMint,
+  getAccount,
+  getMintLen,
+  mintTo,
+} from "@solana/spl-token";
+
+describe("ogc-reserve with a Token-2022 transfer fee staking mint", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const wallet = provider.wallet as anchor.Wallet;
+  const program = anchor.workspace.OgcReserve as Program<OgcReserve>;
+  // 1% fee on every transfer
+  const feeBasisPoints = 100;
+  let oggMint: PublicKey;
+  let signerTokenAccount: PublicKey;
+  it("initializes", async () => {
+    const oggMintKeypair = Keypair.generate();
+    oggMint = oggMintKeypair.publicKey;
+    const mintLen = getMintLen([ExtensionType.TransferFeeConfig]);
+    const lamports = await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+    const transaction = new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: wallet.publicKey,
+        newAccountPubkey: oggMint,
+        space: mintLen,
+        lamports,
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      createInitializeTransferFeeConfigInstruction(
+        oggMint,
+        wallet.publicKey,
+        wallet.publicKey,
+        feeBasisPoints,
+        BigInt(10 ** 6),
+        TOKEN_2022_PROGRAM_ID,
+      ),
+      createInitializeMintInstruction(oggMint, 6, wallet.publicKey, null, TOKEN_2022_PROGRAM_ID),
+    );
+    await sendAndConfirmTransaction(provider.connection, transaction, [wallet.payer, oggMintKeypair]);
+    const ogcMint = await createMint(
+      provider.connection,
+      wallet.payer,
+      wallet.publicKey,
+      wallet.publicKey,
+      6,
+      undefined,
+      undefined,
+      TOKEN_2022_PROGRAM_ID,
+    );
+    signerTokenAccount = await createAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer,
+      oggMint,
+      wallet.publicKey,
+      undefined,
+      TOKEN_2022_PROGRAM_ID,
+    );
+    await mintTo(
+      provider.connection,
+      wallet.payer,
+      oggMint,
+      signerTokenAccount,
+      wallet.payer,
+      100000,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID,
+    )
+    await program.methods.initializeFirstEpochAccount().accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+    await program.methods.initialize().accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      programData,
+      ogcMint,
+      oggMint
+    }).rpc();
+    await program.methods.createDataAccount().accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      mint: oggMint,
+    }).rpc();
+    await program.methods.createStatsAccount().accounts({
+      signer: wallet.publicKey
+    }).rpc();
+    await program.methods.createLockAccount(new BN(0)).accounts({
+      signer: wallet.publicKey
+    }).rpc();
+  })
+  it("credits locks with the amount received after the transfer fee", async () => {
+    await program.methods.lock(new BN(0), new BN(10000), 0).accounts({
+      signer: wallet.publicKey,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      signerTokenAccount,
+    }).rpc();
+    const net = new BN(10000 - 10000 * feeBasisPoints / 10000);
+    const [lockAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lock"), wallet.publicKey.toBuffer(), new BN(0).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [userDataAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("data"), wallet.publicKey.toBuffer()],
+      program.programId
+    );
+    const [signerHolderAccountAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("holder"), wallet.publicKey.toBuffer()],
+      program.programId
+    );
+    const lockAccount = await program.account.lockAccount.fetch(lockAccountAddress);
+    const userDataAccount = await program.account.userDataAccount.fetch(userDataAccountAddress);
+    const signerHolderAccount = await getAccount(provider.connection, signerHolderAccountAddress, undefined, TOKEN_2022_PROGRAM_ID);
+    assert(lockAccount.amount.eq(net), "Lock not credited with the net amount");
+    assert(userDataAccount.amount.eq(net), "User data not credited with the net amount");
+    assert(new BN(signerHolderAccount.amount.toString()).eq(net), "Incorrect holder balance");
+  })
+});